@@ -0,0 +1,198 @@
+use specs::prelude::*;
+use rltk::{Point, RandomNumberGenerator, RGB};
+use super::*;
+use factions::{FactionTable, Reaction};
+use std::collections::HashSet;
+
+/// How far, in steps, various noises carry.
+pub const WALKING_NOISE: i32 = 3;
+pub const COMBAT_NOISE: i32 = 10;
+pub const EXPLOSION_NOISE: i32 = 16;
+
+/// What a perception roll has to beat to spot a player with no skill and
+/// average agility.
+const BASE_STEALTH: i32 = 12;
+
+// anything this close or closer gets a bonus to spot the player
+const NOTICE_RANGE: i32 = 5;
+
+struct Noise {
+    source: Entity,
+    at: Point,
+    volume: i32,
+}
+
+/// Noises made during a turn, heard together once every system that might
+/// make one has run.
+#[derive(Default)]
+pub struct Noises {
+    noises: Vec<Noise>,
+}
+
+impl Noises {
+    pub fn make(&mut self, source: Entity, at: Point, volume: i32) {
+        self.noises.push(Noise { source, at, volume });
+    }
+}
+
+/// How loud the player is when they walk. Practised sneaks barely make a
+/// sound.
+pub fn walking_noise(stealth: i32) -> i32 {
+    i32::max(1, WALKING_NOISE - stealth / 2)
+}
+
+/// Spreads noises through the map to wake and draw in anything that hears
+/// them, and gives monsters that can see the player a chance to notice them.
+pub struct AwarenessSystem {}
+
+impl<'a> System<'a> for AwarenessSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( ReadExpect<'a, Map>,
+                        Entities<'a>,
+                        ReadExpect<'a, Entity>,
+                        ReadExpect<'a, Point>,
+                        WriteExpect<'a, Noises>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        WriteExpect<'a, GameLog>,
+                        ReadExpect<'a, FactionTable>,
+                        ReadStorage<'a, Faction>,
+                        ReadStorage<'a, Monster>,
+                        ReadStorage<'a, Name>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, ViewShed>,
+                        ReadStorage<'a, MyTurn>,
+                        ReadStorage<'a, Attributes>,
+                        WriteStorage<'a, Skills>,
+                        WriteStorage<'a, Asleep>,
+                        WriteStorage<'a, Unaware>,
+                        WriteStorage<'a, Hunting>,
+                        ReadStorage<'a, Fleeing>,
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            entities,
+            player_entity,
+            player_pos,
+            mut noises,
+            mut rng,
+            mut log,
+            faction_table,
+            factions,
+            monsters,
+            names,
+            positions,
+            viewsheds,
+            turns,
+            attributes,
+            mut skills,
+            mut asleep,
+            mut unaware,
+            mut hunting,
+            fleeing,
+        ) = data;
+
+        for noise in noises.noises.drain(..) {
+            let source_faction = factions.get(noise.source).map(|f| f.name.clone());
+
+            for tile in heard_at(&map, noise.at, noise.volume).iter() {
+                for listener in map.get_tile_content(tile.x, tile.y).iter() {
+                    if *listener == noise.source || monsters.get(*listener).is_none() {
+                        continue;
+                    }
+                    let seen = map.tile_is_visible(tile.x, tile.y);
+
+                    if asleep.remove(*listener).is_some() && seen {
+                        log.entry()
+                            .text(format!("The {} wakes up.", names.get(*listener).map_or("", |n| n.name.as_str())))
+                            .log();
+                    }
+
+                    // only noises made by something worth attacking are worth
+                    // looking into
+                    let hostile = match (factions.get(*listener), &source_faction) {
+                        (Some(faction), Some(source_faction)) => {
+                            faction_table.reaction(&faction.name, source_faction) == Reaction::Attack
+                        }
+                        _ => false,
+                    };
+                    let busy = hunting.get(*listener).is_some() || fleeing.get(*listener).is_some();
+                    if hostile && !busy {
+                        hunting
+                            .insert(*listener, Hunting { target: noise.source, last_known_position: noise.at })
+                            .expect("Unable to insert hunt state");
+                    }
+                }
+            }
+        }
+
+        let stealth = BASE_STEALTH
+            + attributes.get(*player_entity).map_or(0, |a| a.dodge())
+            + skills.get(*player_entity).map_or(0, |s| s.level(Skill::Stealth));
+
+        let mut noticed: Vec<Entity> = Vec::new();
+        let mut unnoticed = 0;
+        for (entity, viewshed, pos, _unaware, _turn) in (&entities, &viewsheds, &positions, &unaware, &turns).join() {
+            if asleep.get(entity).is_some() || !viewshed.visible_tiles.contains(&*player_pos) {
+                continue;
+            }
+
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos) as i32;
+            let perception = rng.roll_dice(1, 20)
+                + attributes.get(entity).map_or(0, |a| Attributes::modifier(a.intellect))
+                + i32::max(0, NOTICE_RANGE - distance);
+
+            if perception >= stealth {
+                noticed.push(entity);
+            } else {
+                unnoticed += 1;
+            }
+        }
+
+        for entity in noticed.iter() {
+            unaware.remove(*entity);
+            if let Some(pos) = positions.get(*entity) {
+                if map.tile_is_visible(pos.x, pos.y) {
+                    log.entry()
+                        .text("The ")
+                        .coloured(names.get(*entity).map_or("", |n| n.name.as_str()), RGB::named(rltk::RED))
+                        .text(" notices you!")
+                        .log();
+                }
+            }
+        }
+        for _i in 0..unnoticed {
+            Skills::practise_for(&mut skills, &mut log, *player_entity, true, Skill::Stealth);
+        }
+    }
+}
+
+/// Every tile a noise reaches. Sound finds its way around corners, but not
+/// through walls.
+fn heard_at(map: &Map, at: Point, volume: i32) -> Vec<Point> {
+    let mut heard: HashSet<Point> = HashSet::new();
+    let mut frontier: Vec<Point> = vec![at];
+    heard.insert(at);
+
+    for _step in 0..volume {
+        let mut next: Vec<Point> = Vec::new();
+        for tile in frontier.iter() {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let neighbour = Point::new(tile.x + dx, tile.y + dy);
+                    let (width, height) = map.get_dimensions();
+                    let in_bounds = neighbour.x >= 0 && neighbour.x < width && neighbour.y >= 0 && neighbour.y < height;
+                    if !in_bounds || heard.contains(&neighbour) || map.get_tile(neighbour.x, neighbour.y) == TileType::Wall {
+                        continue;
+                    }
+                    heard.insert(neighbour);
+                    next.push(neighbour);
+                }
+            }
+        }
+        frontier = next;
+    }
+
+    heard.into_iter().collect()
+}
@@ -0,0 +1,123 @@
+use super::*;
+use rltk::Point;
+
+/// Size of the part of the screen the map is drawn into.
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// Which part of the map is on screen. The view is centred on the player, but
+/// stops at the edges of the map rather than showing empty space beyond them.
+pub struct Camera {
+    origin: Point,
+    map_width: i32,
+    map_height: i32,
+}
+
+impl Camera {
+    pub fn new(ecs: &World) -> Camera {
+        let player_pos = ecs.fetch::<Point>();
+        let map = ecs.fetch::<Map>();
+        let (map_width, map_height) = map.get_dimensions();
+        Camera::centred_on(*player_pos, map_width, map_height)
+    }
+
+    /// A view of a `map_width` by `map_height` map centred on `centre`, as far
+    /// as the edges of the map allow.
+    pub fn centred_on(centre: Point, map_width: i32, map_height: i32) -> Camera {
+        Camera {
+            origin: Point::new(
+                view_origin(centre.x, VIEW_WIDTH, map_width),
+                view_origin(centre.y, VIEW_HEIGHT, map_height),
+            ),
+            map_width,
+            map_height,
+        }
+    }
+
+    /// Where a map position is drawn, or `None` if it is out of view.
+    pub fn world_to_screen(&self, world: Point) -> Option<Point> {
+        let screen = Point::new(world.x - self.origin.x, world.y - self.origin.y);
+        if screen.x < 0 || screen.x >= VIEW_WIDTH || screen.y < 0 || screen.y >= VIEW_HEIGHT {
+            return None;
+        }
+        Some(screen)
+    }
+
+    /// The map position under a screen cell, or `None` if the cell isn't
+    /// showing any of the map.
+    pub fn screen_to_world(&self, screen: Point) -> Option<Point> {
+        if screen.x < 0 || screen.x >= VIEW_WIDTH || screen.y < 0 || screen.y >= VIEW_HEIGHT {
+            return None;
+        }
+        let world = Point::new(screen.x + self.origin.x, screen.y + self.origin.y);
+        if world.x >= self.map_width || world.y >= self.map_height {
+            return None;
+        }
+        Some(world)
+    }
+}
+
+fn view_origin(centre: i32, view_size: i32, map_size: i32) -> i32 {
+    if map_size <= view_size {
+        return 0;
+    }
+    i32::max(0, i32::min(centre - view_size / 2, map_size - view_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_stops_at_the_top_left_edge() {
+        let camera = Camera::centred_on(Point::new(3, 2), VIEW_WIDTH * 2, VIEW_HEIGHT * 2);
+        assert_eq!(camera.world_to_screen(Point::new(3, 2)), Some(Point::new(3, 2)));
+        assert_eq!(camera.screen_to_world(Point::new(0, 0)), Some(Point::new(0, 0)));
+    }
+
+    #[test]
+    fn view_stops_at_the_bottom_right_edge() {
+        let (width, height) = (VIEW_WIDTH * 2, VIEW_HEIGHT * 2);
+        let camera = Camera::centred_on(Point::new(width - 2, height - 1), width, height);
+        let corner = Point::new(VIEW_WIDTH - 1, VIEW_HEIGHT - 1);
+        assert_eq!(camera.screen_to_world(corner), Some(Point::new(width - 1, height - 1)));
+        assert_eq!(camera.world_to_screen(Point::new(width - VIEW_WIDTH - 1, 0)), None);
+    }
+
+    #[test]
+    fn view_is_centred_away_from_the_edges() {
+        let (width, height) = (VIEW_WIDTH * 2, VIEW_HEIGHT * 2);
+        let camera = Camera::centred_on(Point::new(width / 2, height / 2), width, height);
+        let centre = Point::new(VIEW_WIDTH / 2, VIEW_HEIGHT / 2);
+        assert_eq!(camera.world_to_screen(Point::new(width / 2, height / 2)), Some(centre));
+    }
+
+    #[test]
+    fn small_maps_start_at_the_corner() {
+        let camera = Camera::centred_on(Point::new(15, 10), 30, 20);
+        assert_eq!(camera.world_to_screen(Point::new(0, 0)), Some(Point::new(0, 0)));
+        // the rest of the screen shows none of the map
+        assert_eq!(camera.screen_to_world(Point::new(30, 5)), None);
+        assert_eq!(camera.screen_to_world(Point::new(5, 20)), None);
+    }
+
+    #[test]
+    fn screen_cells_off_the_view_are_not_on_the_map() {
+        let camera = Camera::centred_on(Point::new(0, 0), VIEW_WIDTH * 2, VIEW_HEIGHT * 2);
+        assert_eq!(camera.screen_to_world(Point::new(-1, 0)), None);
+        assert_eq!(camera.screen_to_world(Point::new(VIEW_WIDTH, 0)), None);
+        assert_eq!(camera.screen_to_world(Point::new(0, VIEW_HEIGHT)), None);
+    }
+
+    #[test]
+    fn conversions_survive_a_round_trip() {
+        let camera = Camera::centred_on(Point::new(100, 50), VIEW_WIDTH * 2, VIEW_HEIGHT * 2);
+        for x in (0..VIEW_WIDTH).step_by(7) {
+            for y in (0..VIEW_HEIGHT).step_by(5) {
+                let screen = Point::new(x, y);
+                let world = camera.screen_to_world(screen).unwrap();
+                assert_eq!(camera.world_to_screen(world), Some(screen));
+            }
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use specs::prelude::*;
 use specs_derive::*;
-use rltk::{RGB, Point};
+use rltk::{DiceType, RGB, Point};
 use serde::{Serialize, Deserialize};
 use specs::saveload::{Marker, ConvertSaveload};
 use specs::error::NoError;
+use std::collections::HashMap;
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Position {
@@ -11,7 +12,7 @@ pub struct Position {
     pub y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, ConvertSaveload, Clone)]
 pub struct Renderable {
     pub glyph: u8,
     pub foreground: RGB,
@@ -19,121 +20,465 @@ pub struct Renderable {
     pub render_order: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct LeftMover {}
 
-#[derive(Component, Debug)]
-pub struct Player {}
-
-#[derive(Component)]
+#[derive(Component, ConvertSaveload, Clone)]
 pub struct ViewShed {
     pub visible_tiles : Vec<Point>,
     pub range: i32,
     pub dirty: bool,
 }
 
-#[derive(Component)]
+/// Lights up the tiles around it, fading out towards the edge of its range.
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct LightSource {
+    pub colour: RGB,
+    pub range: i32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Wandering {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Hunting {
+    pub target: Entity,
+    pub last_known_position: Point,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Fleeing {
+    pub from: Vec<Point>,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Initiative {
+    pub speed: i32,
+    pub energy: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MyTurn {}
+
+/// Does nothing at all until a noise or a blow wakes it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Asleep {}
+
+/// Hasn't noticed the player yet, and won't go after them until it does.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Unaware {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Faction {
+    pub name: String,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
     pub name: String
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct CombatStats {
     pub max_hp: i32,
     pub current_hp: i32,
     pub defense: i32,
-    pub power: i32,
 }
 
+/// What an attack roll has to beat against something with no defense at all.
+const BASE_ARMOUR_CLASS: i32 = 10;
+
 impl CombatStats {
-    pub fn new(hp: i32, defense: i32, power: i32) -> CombatStats {
+    pub fn new(hp: i32, defense: i32) -> CombatStats {
         CombatStats {
             max_hp: hp,
             current_hp: hp,
-            defense: defense,
-            power: power,
+            defense,
         }
     }
+
+    /// Armour plus whatever dodging its attributes allow for.
+    pub fn armour_class(&self, attributes: Option<&Attributes>) -> i32 {
+        BASE_ARMOUR_CLASS + self.defense + attributes.map_or(0, |a| a.dodge())
+    }
 }
 
-#[derive(Component, Debug, Clone)]
+/// An attribute that neither helps nor hinders.
+pub const AVERAGE_ATTRIBUTE: i32 = 10;
+
+// how far something of average intellect notices things
+const BASE_VIEW_RANGE: i32 = 8;
+
+// extra maximum hit points for every level gained, before toughness
+const BASE_HP_PER_LEVEL: i32 = 5;
+
+/// What something is made of. How well it fights, how much punishment it
+/// takes and how far it sees are all worked out from these.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Attributes {
+    pub might: i32,
+    pub agility: i32,
+    pub toughness: i32,
+    pub intellect: i32,
+}
+
+impl Attributes {
+    pub fn new(might: i32, agility: i32, toughness: i32, intellect: i32) -> Attributes {
+        Attributes {
+            might,
+            agility,
+            toughness,
+            intellect,
+        }
+    }
+
+    /// How much an attribute adds to, or takes off, anything that depends on
+    /// it: a point for every two above average.
+    pub fn modifier(value: i32) -> i32 {
+        (value - AVERAGE_ATTRIBUTE).div_euclid(2)
+    }
+
+    pub fn max_hp(&self) -> i32 {
+        i32::max(1, self.toughness * 2)
+    }
+
+    pub fn hp_per_level(&self) -> i32 {
+        i32::max(1, BASE_HP_PER_LEVEL + Attributes::modifier(self.toughness))
+    }
+
+    pub fn to_hit(&self) -> i32 {
+        Attributes::modifier(self.might)
+    }
+
+    pub fn damage_bonus(&self) -> i32 {
+        Attributes::modifier(self.might)
+    }
+
+    pub fn dodge(&self) -> i32 {
+        Attributes::modifier(self.agility)
+    }
+
+    pub fn view_range(&self) -> i32 {
+        i32::max(1, BASE_VIEW_RANGE + Attributes::modifier(self.intellect))
+    }
+}
+
+/// Something a creature gets better at by doing it.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Skill {
+    Melee,
+    Ranged,
+    Tech,
+    Stealth,
+}
+
+impl Skill {
+    pub const ALL: [Skill; 4] = [Skill::Melee, Skill::Ranged, Skill::Tech, Skill::Stealth];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Skill::Melee => "Melee",
+            Skill::Ranged => "Ranged",
+            Skill::Tech => "Tech",
+            Skill::Stealth => "Stealth",
+        }
+    }
+}
+
+// uses it takes to go from one skill level to the next, times the next level
+const PRACTICE_PER_LEVEL: i32 = 10;
+pub const MAX_SKILL_LEVEL: i32 = 10;
+
+/// How good something is at each skill, and how far it has got towards the
+/// next level. Skills nobody has practised are at level 0.
+#[derive(Component, Debug, ConvertSaveload, Clone, Default)]
+pub struct Skills {
+    pub levels: HashMap<Skill, i32>,
+    pub practice: HashMap<Skill, i32>,
+}
+
+impl Skills {
+    pub fn level(&self, skill: Skill) -> i32 {
+        *self.levels.get(&skill).unwrap_or(&0)
+    }
+
+    pub fn practice(&self, skill: Skill) -> i32 {
+        *self.practice.get(&skill).unwrap_or(&0)
+    }
+
+    pub fn practice_to_next_level(&self, skill: Skill) -> i32 {
+        (self.level(skill) + 1) * PRACTICE_PER_LEVEL
+    }
+
+    /// Counts one more use of a skill. Returns the new level if that was
+    /// enough to gain one.
+    pub fn practise(&mut self, skill: Skill) -> Option<i32> {
+        if self.level(skill) >= MAX_SKILL_LEVEL {
+            return None;
+        }
+
+        let practice = self.practice(skill) + 1;
+        if practice < self.practice_to_next_level(skill) {
+            self.practice.insert(skill, practice);
+            return None;
+        }
+
+        let level = self.level(skill) + 1;
+        self.practice.insert(skill, 0);
+        self.levels.insert(skill, level);
+        Some(level)
+    }
+
+    /// Practises a skill for anything that has skills, and lets the player
+    /// know when theirs improve.
+    pub fn practise_for(
+        store: &mut WriteStorage<Skills>,
+        log: &mut super::game_log::GameLog,
+        entity: Entity,
+        is_player: bool,
+        skill: Skill,
+    ) {
+        let gained = store.get_mut(entity).and_then(|skills| skills.practise(skill));
+        if let (Some(level), true) = (gained, is_player) {
+            log.entry()
+                .text(format!("Your {} skill rises to ", skill.name().to_lowercase()))
+                .coloured(format!("level {}", level), RGB::named(rltk::CYAN))
+                .text("!")
+                .log();
+        }
+    }
+}
+
+/// Marks an item as a piece of tech, which works better for those with the
+/// skill to use it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TechItem {}
+
+/// Makes an item something to fight with once it is wielded.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct MeleeWeapon {
+    pub damage: DiceType,
+    pub hit_bonus: i32,
+}
+
+/// How something fights when it has no weapon in hand.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct NaturalAttack {
+    pub name: String,
+    pub damage: DiceType,
+    pub hit_bonus: i32,
+}
+
+/// Marks an item in someone's backpack as the one they are using.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Experience {
+    pub xp: i32,
+    pub level: i32,
+}
+
+impl Experience {
+    pub fn xp_to_next_level(&self) -> i32 {
+        self.level * 50
+    }
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToMelee {
     pub target: Entity
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>
+    pub amount: Vec<(i32, bool)>
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32, from_player: bool) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, from_player));
         } else {
-            let dmg = SufferDamage { amount: vec![amount] };
+            let dmg = SufferDamage { amount: vec![(amount, from_player)] };
             store.insert(victim, dmg).expect("unable to insert damage");
         }
     }
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Description {
+    pub text: String
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct ProvidesHealing {
     pub heal_amount: i32
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InBackPack {
     pub owner: Entity
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToPickupItem {
     pub collected_by: Entity,
     pub item: Entity,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToUseItem {
     pub item: Entity,
     pub target: Option<rltk::Point>,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToDropItem {
     pub item: Entity
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Consumable {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Value {
+    pub price: i32
+}
+
+/// Money. All of it that someone carries is kept in a single item.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Currency {
+    pub amount: i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vendor {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Identifies {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct UsesItems {}
+
+/// Leaves the named item behind when it dies, instead of a corpse.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct LeavesWreckage {
+    pub item: String,
+}
+
+/// What a monster might leave behind when it dies: item names with relative
+/// weights, and the weight of leaving nothing at all.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct LootTable {
+    pub drops: Vec<(String, i32)>,
+    pub nothing: i32,
+}
+
+impl LootTable {
+    pub fn roll(&self, rng: &mut rltk::RandomNumberGenerator) -> Option<String> {
+        let total = self.nothing + self.drops.iter().map(|(_, weight)| weight).sum::<i32>();
+        if total < 1 {
+            return None;
+        }
+
+        let mut roll = rng.roll_dice(1, total);
+        if roll <= self.nothing {
+            return None;
+        }
+
+        roll -= self.nothing;
+        for (name, weight) in self.drops.iter() {
+            if roll <= *weight {
+                return Some(name.to_string());
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ThrowsItems {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Ranged {
     pub range: i32
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InflictsDamage {
     pub damage: i32
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct AreaOfEffect {
     pub radius: i32
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Confusion {
     pub turns: i32
 }
 
-pub struct SerializeMe;
\ No newline at end of file
+/// Heals faster than usual for a while.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Invigorated {
+    pub turns: i32
+}
+
+/// Invigorates whoever uses it for `turns` turns.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct GrantsInvigoration {
+    pub turns: i32
+}
+
+/// Shows the layout of the whole ship to whoever uses it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RevealsMap {}
+
+/// Picks up every creature aboard, wherever it is, for a while.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Detection {
+    pub turns: i32
+}
+
+/// Lets whoever uses it detect creatures for `turns` turns.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct GrantsDetection {
+    pub turns: i32
+}
+
+/// Heals on its own, a point every `interval` turns.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Regenerates {
+    pub interval: i32
+}
+
+/// Counts down the real time a particle has left. Particles are never saved.
+#[derive(Component, Debug, Clone)]
+pub struct ParticleLifetime {
+    pub lifetime_ms: f32
+}
+
+pub struct SerializeMe;
+
+// carries everything that isn't a component into the save file
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct SerializationHelper {
+    pub map: super::map::Map,
+    pub log: super::game_log::GameLog,
+    pub stats: super::run_stats::RunStats,
+    pub identification: super::identification::Identification,
+}
\ No newline at end of file
@@ -0,0 +1,39 @@
+use specs::prelude::*;
+use rltk::RGB;
+use super::*;
+
+/// Runs down the player's confusion, a turn at a time. Monsters shake off
+/// theirs in `MonsterAI`, on their own turns.
+pub struct ConfusionSystem {}
+
+impl<'a> System<'a> for ConfusionSystem {
+    type SystemData = ( ReadExpect<'a, Entity>,
+                        ReadExpect<'a, RunState>,
+                        WriteExpect<'a, GameLog>,
+                        WriteStorage<'a, Confusion>,
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, runstate, mut gamelog, mut confused) = data;
+
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        let cleared = match confused.get_mut(*player_entity) {
+            Some(confusion) => {
+                confusion.turns -= 1;
+                confusion.turns < 1
+            }
+            None => false,
+        };
+        if cleared {
+            confused.remove(*player_entity);
+            gamelog.entry()
+                .text("You are no longer ")
+                .coloured("confused", RGB::named(rltk::MAGENTA))
+                .text(".")
+                .log();
+        }
+    }
+}
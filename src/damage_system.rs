@@ -1,21 +1,59 @@
 use specs::prelude::*;
-use rltk::{console};
+use rltk::{console, RGB};
 use super::*;
 
 pub struct DamageSystem {}
 
 impl <'a> System<'a> for DamageSystem {
-    type SystemData = ( WriteStorage<'a, CombatStats>,
-                        WriteStorage<'a, SufferDamage>);
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        WriteStorage<'a, CombatStats>,
+                        WriteStorage<'a, SufferDamage>,
+                        WriteStorage<'a, Experience>,
+                        ReadStorage<'a, Attributes>,
+                        ReadExpect<'a, Entity>,
+                        WriteExpect<'a, RunStats>,
+                        WriteExpect<'a, GameLog>,
+                        WriteStorage<'a, Asleep>,
+                        WriteStorage<'a, Unaware>);
 
     fn run (&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (entities, mut stats, mut damage, mut experience, attributes, player_entity, mut run_stats, mut log, mut asleep, mut unaware) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
-            stats.current_hp -= damage.amount.iter().sum::<i32>();
+        let mut xp_gained = 0;
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            // nothing sleeps through being hurt, or fails to notice it
+            asleep.remove(entity);
+            unaware.remove(entity);
+
+            let was_alive = stats.current_hp > 0;
+            stats.current_hp -= damage.amount.iter().map(|(amount, _)| amount).sum::<i32>();
+
+            // the player gets the credit if any of the killing blows were theirs
+            if was_alive && stats.current_hp < 1 && damage.amount.iter().any(|(_, from_player)| *from_player) {
+                xp_gained += stats.max_hp;
+                run_stats.kills += 1;
+            }
         }
 
         damage.clear();
+
+        if xp_gained > 0 {
+            if let Some(exp) = experience.get_mut(*player_entity) {
+                exp.xp += xp_gained;
+                while exp.xp >= exp.xp_to_next_level() {
+                    exp.xp -= exp.xp_to_next_level();
+                    exp.level += 1;
+                    if let Some(player_stats) = stats.get_mut(*player_entity) {
+                        player_stats.max_hp += attributes.get(*player_entity).map_or(1, |a| a.hp_per_level());
+                        player_stats.current_hp = player_stats.max_hp;
+                    }
+                    log.entry()
+                        .coloured(format!("Welcome to level {}!", exp.level), RGB::named(rltk::MAGENTA))
+                        .log();
+                }
+            }
+        }
     }
 }
 
@@ -37,7 +75,7 @@ pub fn delete_the_dead(ecs: &mut World) {
                     None => {
                         let victim_name = names.get(entity);
                         if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("{} is dead", victim_name.name));
+                            log.log(format!("{} is dead", victim_name.name));
                         }
                         dead.push(entity);
                     },
@@ -47,7 +85,60 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    // empty their pockets before they go, so no item is left with a deleted owner
+    for victim in dead.iter() {
+        leave_remains(ecs, *victim);
+    }
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+}
+
+fn leave_remains(ecs: &mut World, victim: Entity) {
+    let (x, y, name, loot, wreckage) = {
+        let positions = ecs.read_storage::<Position>();
+        let names = ecs.read_storage::<Name>();
+        let loot_tables = ecs.read_storage::<LootTable>();
+        let wreckage = ecs.read_storage::<LeavesWreckage>();
+        let pos = match positions.get(victim) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let name = names.get(victim).map_or(String::new(), |n| n.name.to_string());
+        (pos.x, pos.y, name, loot_tables.get(victim).cloned(), wreckage.get(victim).map(|w| w.item.clone()))
+    };
+
+    {
+        let entities = ecs.entities();
+        let mut backpack = ecs.write_storage::<InBackPack>();
+        let mut positions = ecs.write_storage::<Position>();
+        let mut equipped = ecs.write_storage::<Equipped>();
+        let carried: Vec<Entity> = (&entities, &backpack)
+            .join()
+            .filter(|(_, pack)| pack.owner == victim)
+            .map(|(item, _)| item)
+            .collect();
+        for item in carried {
+            backpack.remove(item);
+            equipped.remove(item);
+            positions.insert(item, Position { x, y }).expect("Unable to insert position");
+        }
+    }
+
+    if let Some(loot) = loot {
+        let drop = loot.roll(&mut ecs.write_resource::<rltk::RandomNumberGenerator>());
+        if let Some(drop) = drop {
+            spawner::named_item(ecs, &drop, x, y);
+        }
+    }
+
+    match wreckage {
+        Some(wreckage) => {
+            spawner::named_item(ecs, &wreckage, x, y);
+        }
+        None => {
+            spawner::corpse(ecs, &name, x, y);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,42 @@
+use specs::prelude::*;
+use rltk::RGB;
+use super::*;
+
+/// Runs down detection, a turn at a time. Like regeneration, it only counts
+/// the player's turns.
+pub struct DetectionSystem {}
+
+impl<'a> System<'a> for DetectionSystem {
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, Entity>,
+                        ReadExpect<'a, RunState>,
+                        WriteExpect<'a, GameLog>,
+                        WriteStorage<'a, Detection>,
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, runstate, mut gamelog, mut detection) = data;
+
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        let mut worn_off: Vec<Entity> = Vec::new();
+        for (entity, status) in (&entities, &mut detection).join() {
+            status.turns -= 1;
+            if status.turns < 1 {
+                worn_off.push(entity);
+            }
+        }
+        for entity in worn_off.iter() {
+            detection.remove(*entity);
+            if *entity == *player_entity {
+                gamelog.entry()
+                    .text("Your tracker's ")
+                    .coloured("signal", RGB::named(rltk::MAGENTA))
+                    .text(" fades.")
+                    .log();
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use rltk::DiceType;
+
+/// Reads dice written the usual way, such as "1d6+2". Only meant for dice
+/// written into the game itself, so a typo is a bug.
+pub fn dice(text: &str) -> DiceType {
+    rltk::parse_dice_string(text).unwrap_or_else(|_| panic!("Invalid dice string: {}", text))
+}
+
+/// Writes dice back out the way `dice` reads them.
+pub fn dice_string(dice: DiceType) -> String {
+    match dice.bonus {
+        0 => format!("{}d{}", dice.n_dice, dice.die_type),
+        bonus if bonus > 0 => format!("{}d{}+{}", dice.n_dice, dice.die_type, bonus),
+        bonus => format!("{}d{}{}", dice.n_dice, dice.die_type, bonus),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dice_survive_a_round_trip() {
+        for text in ["1d6", "1d6+2", "2d4-1", "1d20"].iter() {
+            assert_eq!(dice_string(dice(text)), *text);
+        }
+    }
+
+    #[test]
+    fn dice_are_read_in_full() {
+        let read = dice("3d8+4");
+        assert_eq!((read.n_dice, read.die_type, read.bonus), (3, 8, 4));
+    }
+}
@@ -0,0 +1,139 @@
+use super::Map;
+use rltk::BaseMap;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+pub const UNREACHABLE: f32 = f32::MAX;
+
+// how strongly a flee map pulls away from the goals; anything past -1.0 lets
+// fleeing creatures look beyond the nearest dead end for a real escape route
+const FLEE_FACTOR: f32 = -1.2;
+
+/// How far every tile is from the nearest of a set of goals. Anything can find
+/// its way to a goal by repeatedly stepping to its lowest neighbour, so one map
+/// serves every creature heading for the same goals.
+#[derive(Default, Clone)]
+pub struct DistanceMap {
+    distances: Vec<f32>,
+    // whether only walls are in the way, rather than anything that blocks
+    walls_only: bool,
+}
+
+#[derive(PartialEq)]
+struct Frontier {
+    idx: usize,
+    distance: f32,
+}
+
+impl Eq for Frontier {}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed, so the heap pops the closest tile first
+        other
+            .distance
+            .partial_cmp(&self.distance)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl DistanceMap {
+    pub fn new(map: &Map, goals: &[usize]) -> DistanceMap {
+        DistanceMap::build(map, goals, false)
+    }
+
+    /// Like `new`, but only walls are in the way. Maps that last a whole turn
+    /// use this, or they would be full of holes wherever a monster happened to
+    /// be standing when they were built.
+    pub fn walls_only(map: &Map, goals: &[usize]) -> DistanceMap {
+        DistanceMap::build(map, goals, true)
+    }
+
+    fn build(map: &Map, goals: &[usize], walls_only: bool) -> DistanceMap {
+        let (width, height) = map.get_dimensions();
+        let mut distances = vec![UNREACHABLE; (width * height) as usize];
+        for goal in goals.iter() {
+            distances[*goal] = 0.0;
+        }
+
+        let mut distance_map = DistanceMap { distances, walls_only };
+        distance_map.relax(map);
+        distance_map
+    }
+
+    /// Builds a map that leads away from this map's goals.
+    pub fn flee(&self, map: &Map) -> DistanceMap {
+        let distances = self
+            .distances
+            .iter()
+            .map(|d| {
+                if *d == UNREACHABLE {
+                    UNREACHABLE
+                } else {
+                    *d * FLEE_FACTOR
+                }
+            })
+            .collect();
+
+        let mut distance_map = DistanceMap {
+            distances,
+            walls_only: self.walls_only,
+        };
+        distance_map.relax(map);
+        distance_map
+    }
+
+    pub fn distance(&self, idx: usize) -> f32 {
+        self.distances[idx]
+    }
+
+    /// The neighbouring tile that gets closest to a goal, if any is closer than
+    /// where we are now.
+    pub fn step_down(&self, map: &Map, from: usize) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        let mut best_distance = self.distances[from];
+
+        for (exit, _cost) in map.get_available_exits(from).iter() {
+            if self.distances[*exit] < best_distance {
+                best = Some(*exit);
+                best_distance = self.distances[*exit];
+            }
+        }
+
+        best
+    }
+
+    fn relax(&mut self, map: &Map) {
+        let mut frontier: BinaryHeap<Frontier> = self
+            .distances
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d != UNREACHABLE)
+            .map(|(idx, d)| Frontier { idx, distance: *d })
+            .collect();
+
+        while let Some(Frontier { idx, distance }) = frontier.pop() {
+            if distance > self.distances[idx] {
+                // already reached this tile by a shorter route
+                continue;
+            }
+
+            for (exit, cost) in map.exits(idx, self.walls_only).iter() {
+                let next = distance + cost;
+                if next < self.distances[*exit] {
+                    self.distances[*exit] = next;
+                    frontier.push(Frontier {
+                        idx: *exit,
+                        distance: next,
+                    });
+                }
+            }
+        }
+    }
+}
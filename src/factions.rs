@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+pub const PLAYER: &str = "Player";
+pub const ORCS: &str = "Orcs";
+pub const GOBLINS: &str = "Goblins";
+pub const MACHINES: &str = "Machines";
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Reaction {
+    Attack,
+    Ignore,
+    Flee,
+}
+
+pub struct FactionTable {
+    reactions: HashMap<(String, String), Reaction>,
+}
+
+impl FactionTable {
+    pub fn new() -> FactionTable {
+        let mut table = FactionTable {
+            reactions: HashMap::new(),
+        };
+
+        table.set_reaction(ORCS, PLAYER, Reaction::Attack);
+        table.set_reaction(GOBLINS, PLAYER, Reaction::Attack);
+        table.set_reaction(MACHINES, PLAYER, Reaction::Attack);
+
+        // orcs prey on goblins, who know better than to stick around
+        table.set_reaction(ORCS, GOBLINS, Reaction::Attack);
+        table.set_reaction(GOBLINS, ORCS, Reaction::Flee);
+
+        table
+    }
+
+    pub fn set_reaction<S: ToString>(&mut self, from: S, towards: S, reaction: Reaction) {
+        self.reactions
+            .insert((from.to_string(), towards.to_string()), reaction);
+    }
+
+    /// How members of `from` behave towards members of `towards`. Anything
+    /// not in the table is ignored, including members of the same faction.
+    pub fn reaction(&self, from: &str, towards: &str) -> Reaction {
+        match self.reactions.get(&(from.to_string(), towards.to_string())) {
+            Some(reaction) => *reaction,
+            None => Reaction::Ignore,
+        }
+    }
+}
+
+impl Default for FactionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
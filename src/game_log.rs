@@ -1,3 +1,78 @@
+use rltk::RGB;
+use serde::{Deserialize, Serialize};
+
+// older entries are dropped once the history gets this long
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LogSegment {
+    pub text: String,
+    pub colour: RGB,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LogEntry {
+    pub turn: i32,
+    pub segments: Vec<LogSegment>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct GameLog {
-    pub entries: Vec<String>
-}
\ No newline at end of file
+    pub entries: Vec<LogEntry>,
+    pub turn: i32,
+}
+
+impl GameLog {
+    /// Logs a single line of plain text.
+    pub fn log<S: ToString>(&mut self, text: S) {
+        self.entry().text(text).log();
+    }
+
+    /// Starts building an entry out of differently coloured pieces.
+    pub fn entry(&mut self) -> LogBuilder<'_> {
+        LogBuilder {
+            log: self,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Entries logged from now on are stamped with this turn.
+    pub fn set_turn(&mut self, turn: i32) {
+        self.turn = turn;
+    }
+
+    fn push(&mut self, segments: Vec<LogSegment>) {
+        self.entries.push(LogEntry {
+            turn: self.turn,
+            segments,
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(0..excess);
+        }
+    }
+}
+
+#[must_use = "the entry isn't logged until log() is called"]
+pub struct LogBuilder<'a> {
+    log: &'a mut GameLog,
+    segments: Vec<LogSegment>,
+}
+
+impl<'a> LogBuilder<'a> {
+    pub fn text<S: ToString>(self, text: S) -> Self {
+        self.coloured(text, RGB::named(rltk::WHITE))
+    }
+
+    pub fn coloured<S: ToString>(mut self, text: S, colour: RGB) -> Self {
+        self.segments.push(LogSegment {
+            text: text.to_string(),
+            colour,
+        });
+        self
+    }
+
+    pub fn log(self) {
+        self.log.push(self.segments);
+    }
+}
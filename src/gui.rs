@@ -1,11 +1,22 @@
 use super::*;
-use rltk::{Console, Rltk, VirtualKeyCode, RGB};
+use camera::Camera;
+use inventory_system::{stack_items, ItemStack};
+use dice::dice_string;
+use keymap::{key_name, Action, Context, Keymap};
+use rltk::{Console, Rltk, RGB};
+
+// one letter for each stack on a page of the inventory
+const ITEMS_PER_PAGE: usize = 26;
+
+// width of the text inside a description panel
+const PANEL_TEXT_WIDTH: usize = 28;
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum ItemMenuResult {
     Cancel,
     NoResponse,
     Selected,
+    Highlight { selection: usize },
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -21,8 +32,42 @@ pub enum MainMenuResult {
     Selected { selected: MainMenuSelection },
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum TradeResult {
+    Cancel,
+    NoResponse { selling: bool, selection: usize },
+    Buy(Entity),
+    Sell(Entity),
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LookResult {
+    Cancel,
+    NoResponse { cursor: Point },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum DestinationResult {
+    Cancel,
+    NoResponse { cursor: Point },
+    Selected { destination: Point },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CreationResult {
+    NoResponse { selection: usize },
+    Done,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LogScreenResult {
+    Cancel,
+    NoResponse { offset: usize },
+}
+
 pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     let run_state = gs.ecs.fetch::<RunState>();
+    let save_exists = saveload_system::does_save_exist();
 
     let yellow = RGB::named(rltk::YELLOW);
     let black = RGB::named(rltk::BLACK);
@@ -38,10 +83,12 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
             ctx.print_color_centered(24, white, black, "New Game");
         }
 
-        if selection == MainMenuSelection::LoadGame {
-            ctx.print_color_centered(25, magenta, black, "Load Game");
-        } else {
-            ctx.print_color_centered(25, white, black, "Load Game");
+        if save_exists {
+            if selection == MainMenuSelection::LoadGame {
+                ctx.print_color_centered(25, magenta, black, "Load Game");
+            } else {
+                ctx.print_color_centered(25, white, black, "Load Game");
+            }
         }
 
         if selection == MainMenuSelection::Quit {
@@ -50,33 +97,38 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
             ctx.print_color_centered(26, white, black, "Quit Game");
         }
 
-        match ctx.key {
+        let action = gs.ecs.fetch::<Keymap>().action(ctx.key, Context::Menu);
+        match action {
             None => return MainMenuResult::NoSelection{ selected: selection },
-            Some(key) => {
-                match key {
-                    VirtualKeyCode::Escape => { return MainMenuResult::NoSelection{ selected: selection } }
-
-                    VirtualKeyCode::Up => {
-                        let new_selection;
-                        match selection {
-                            MainMenuSelection::NewGame => new_selection = MainMenuSelection::Quit,
-                            MainMenuSelection::LoadGame => new_selection = MainMenuSelection::NewGame,
-                            MainMenuSelection::Quit => new_selection = MainMenuSelection::LoadGame
+            Some(action) => {
+                match action {
+                    Action::MenuCancel => { return MainMenuResult::NoSelection{ selected: selection } }
+
+                    Action::MenuUp => {
+                        let mut new_selection = match selection {
+                            MainMenuSelection::NewGame => MainMenuSelection::Quit,
+                            MainMenuSelection::LoadGame => MainMenuSelection::NewGame,
+                            MainMenuSelection::Quit => MainMenuSelection::LoadGame
+                        };
+                        if new_selection == MainMenuSelection::LoadGame && !save_exists {
+                            new_selection = MainMenuSelection::NewGame;
                         }
                         return MainMenuResult::NoSelection{ selected: new_selection }
                     }
 
-                    VirtualKeyCode::Down => {
-                        let new_selection;
-                        match selection {
-                            MainMenuSelection::NewGame => new_selection = MainMenuSelection::LoadGame,
-                            MainMenuSelection::LoadGame => new_selection = MainMenuSelection::Quit,
-                            MainMenuSelection::Quit => new_selection = MainMenuSelection::NewGame
+                    Action::MenuDown => {
+                        let mut new_selection = match selection {
+                            MainMenuSelection::NewGame => MainMenuSelection::LoadGame,
+                            MainMenuSelection::LoadGame => MainMenuSelection::Quit,
+                            MainMenuSelection::Quit => MainMenuSelection::NewGame
+                        };
+                        if new_selection == MainMenuSelection::LoadGame && !save_exists {
+                            new_selection = MainMenuSelection::Quit;
                         }
                         return MainMenuResult::NoSelection{ selected: new_selection }
                     }
 
-                    VirtualKeyCode::Return => return MainMenuResult::Selected{ selected: selection },
+                    Action::MenuSelect => return MainMenuResult::Selected{ selected: selection },
 
                     _ => return MainMenuResult::NoSelection{ selected: selection }
                 }
@@ -121,182 +173,700 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         );
     }
 
-    let mut y = 44;
-    for s in log.entries.iter() {
-        if y < 49 {
-            ctx.print(2, y, s);
-        }
-        y += 1;
+    // newest first
+    for (y, entry) in (44..49).zip(log.entries.iter().rev()) {
+        print_log_entry(ctx, 2, y, 78, entry);
     }
 
     draw_tooltips(ecs, ctx);
 }
 
-pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+pub fn show_inventory(gs: &mut State, ctx: &mut Rltk, selection: usize) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let stacks = carried_stacks(&gs.ecs, player_entity);
+    item_menu(&gs.ecs, ctx, "Inventory", &stacks, &[], selection)
+}
+
+// every attribute, in the order they are shown
+fn attribute_list(attributes: &Attributes) -> [(&'static str, i32); 4] {
+    [
+        ("Might", attributes.might),
+        ("Agility", attributes.agility),
+        ("Toughness", attributes.toughness),
+        ("Intellect", attributes.intellect),
+    ]
+}
+
+// points to spend on attributes when creating a character, and how far each
+// attribute can be pushed either way
+const CREATION_POINTS: i32 = 10;
+const MIN_ATTRIBUTE: i32 = 8;
+const MAX_ATTRIBUTE: i32 = 18;
+
+/// Lets the player spend points on their attributes before the game starts.
+/// Lowering an attribute below average frees up points for the others.
+pub fn character_creation(gs: &mut State, ctx: &mut Rltk, selection: usize) -> CreationResult {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let mut attributes = match gs.ecs.read_storage::<Attributes>().get(player_entity) {
+        Some(attributes) => attributes.clone(),
+        None => return CreationResult::Done,
+    };
+    let defense = gs.ecs.read_storage::<CombatStats>().get(player_entity).map_or(0, |s| s.defense);
+
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let yellow = RGB::named(rltk::YELLOW);
+    let magenta = RGB::named(rltk::MAGENTA);
+    let cyan = RGB::named(rltk::CYAN);
+
+    let list = attribute_list(&attributes);
+    let spent: i32 = list.iter().map(|(_, value)| value - AVERAGE_ATTRIBUTE).sum();
+    let remaining = CREATION_POINTS - spent;
+
+    ctx.draw_box(15, 14, 54, 20, white, black);
+    ctx.print_color(18, 14, yellow, black, "Create your character");
+    let keymap = gs.ecs.fetch::<Keymap>();
+    let prompt = format!(
+        "{}/{} choose, {}/{} change, {} to begin",
+        keymap.key_label(Action::MenuUp),
+        keymap.key_label(Action::MenuDown),
+        keymap.key_label(Action::MoveW),
+        keymap.key_label(Action::MoveE),
+        keymap.key_label(Action::MenuSelect)
+    );
+    ctx.print_color(18, 34, yellow, black, &prompt);
+
+    let uses = ["to hit and damage", "dodging blows", "hit points", "how far you see"];
+    for (j, (name, value)) in list.iter().enumerate() {
+        let y = 17 + j as i32 * 2;
+        let colour = if j == selection { magenta } else { white };
+        ctx.print_color(18, y, colour, black, name);
+        ctx.print_color(30, y, colour, black, &format!("{:>2} ({:+})", value, Attributes::modifier(*value)));
+        ctx.print_color(40, y, cyan, black, uses[j]);
+    }
+
+    ctx.print_color(18, 26, white, black, &format!("Points left: {}", remaining));
+    ctx.print_color(
+        18,
+        28,
+        cyan,
+        black,
+        &format!(
+            "HP {}   AC {}   Sight {}",
+            attributes.max_hp(),
+            CombatStats::new(0, defense).armour_class(Some(&attributes)),
+            attributes.view_range()
+        ),
+    );
+    if remaining != 0 {
+        ctx.print_color(18, 31, yellow, black, "Spend every point before you begin.");
+    }
+
+    match keymap.action(ctx.key, Context::Menu) {
+        Some(Action::MenuUp) => {
+            return CreationResult::NoResponse { selection: (selection + list.len() - 1) % list.len() }
+        }
+        Some(Action::MenuDown) => return CreationResult::NoResponse { selection: (selection + 1) % list.len() },
+        Some(Action::MenuSelect) if remaining == 0 => return CreationResult::Done,
+        _ => {}
+    }
+
+    let change = match keymap.action(ctx.key, Context::Game).and_then(|action| action.direction()) {
+        Some((delta_x, 0)) => delta_x,
+        _ => return CreationResult::NoResponse { selection },
+    };
+    let value = match selection {
+        0 => &mut attributes.might,
+        1 => &mut attributes.agility,
+        2 => &mut attributes.toughness,
+        _ => &mut attributes.intellect,
+    };
+    let changed = *value + change;
+    if (MIN_ATTRIBUTE..=MAX_ATTRIBUTE).contains(&changed) && remaining - change >= 0 {
+        *value = changed;
+        gs.ecs
+            .write_storage::<Attributes>()
+            .insert(player_entity, attributes)
+            .expect("Unable to insert attributes");
+    }
+
+    CreationResult::NoResponse { selection }
+}
+
+pub fn show_character_sheet(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
     let player_entity = gs.ecs.fetch::<Entity>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let experience = gs.ecs.read_storage::<Experience>();
+    let confused = gs.ecs.read_storage::<Confusion>();
+    let invigorated = gs.ecs.read_storage::<Invigorated>();
+    let detection = gs.ecs.read_storage::<Detection>();
     let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackPack>();
-    let entities = gs.ecs.entities();
+    let equipped = gs.ecs.read_storage::<Equipped>();
+    let weapons = gs.ecs.read_storage::<MeleeWeapon>();
+    let attributes = gs.ecs.read_storage::<Attributes>();
+    let skills = gs.ecs.read_storage::<Skills>();
+    let run_stats = gs.ecs.fetch::<RunStats>();
 
-    let inventory = (&backpack, &names)
+    let mut lines: Vec<String> = Vec::new();
+    if let Some(exp) = experience.get(*player_entity) {
+        lines.push(format!("Level: {}", exp.level));
+        lines.push(format!("XP: {} / {}", exp.xp, exp.xp_to_next_level()));
+    }
+    if let Some(stats) = combat_stats.get(*player_entity) {
+        lines.push(format!("HP: {} / {}", stats.current_hp, stats.max_hp));
+        let attributes = attributes.get(*player_entity);
+        lines.push(format!("Defense: {} (AC {})", stats.defense, stats.armour_class(attributes)));
+    }
+    if let Some(attributes) = attributes.get(*player_entity) {
+        for (name, value) in attribute_list(attributes).iter() {
+            lines.push(format!("{}: {} ({:+})", name, value, Attributes::modifier(*value)));
+        }
+    }
+    let wielded = (&names, &equipped, &weapons)
         .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+        .find(|(_, e, _)| e.owner == *player_entity)
+        .map(|(name, _, weapon)| format!("Weapon: {} ({})", name.name, dice_string(weapon.damage)));
+    lines.push(wielded.unwrap_or_else(|| "Weapon: Bare hands".to_string()));
+    let mut statuses: Vec<String> = Vec::new();
+    if let Some(confusion) = confused.get(*player_entity) {
+        statuses.push(format!("Confused ({} turns)", confusion.turns));
+    }
+    if let Some(boost) = invigorated.get(*player_entity) {
+        statuses.push(format!("Invigorated ({} turns)", boost.turns));
+    }
+    if let Some(detecting) = detection.get(*player_entity) {
+        statuses.push(format!("Detecting ({} turns)", detecting.turns));
+    }
+    if statuses.is_empty() {
+        statuses.push("Normal".to_string());
+    }
+    for status in statuses.iter() {
+        lines.push(format!("Status: {}", status));
+    }
+    if let Some(skills) = skills.get(*player_entity) {
+        for skill in Skill::ALL.iter() {
+            let progress = if skills.level(*skill) >= MAX_SKILL_LEVEL {
+                "max".to_string()
+            } else {
+                format!("{} / {}", skills.practice(*skill), skills.practice_to_next_level(*skill))
+            };
+            lines.push(format!("{}: {} ({})", skill.name(), skills.level(*skill), progress));
+        }
+    }
+    lines.push(format!("Credits: {}", trade::credits(&gs.ecs, *player_entity)));
+    lines.push(format!("Kills: {}", run_stats.kills));
+    lines.push(format!("Turns: {}", run_stats.turns));
 
     let white = RGB::named(rltk::WHITE);
     let black = RGB::named(rltk::BLACK);
     let yellow = RGB::named(rltk::YELLOW);
 
-    let mut y = (25 - (count / 2)) as i32;
-    ctx.draw_box(15, y - 2, 31, (count + 3) as i32, white, black);
-    ctx.print_color(18, y - 2, yellow, black, "Inventory");
-    ctx.print_color(18, y + count as i32 + 1, yellow, black, "ESCAPE to cancel");
+    let count = lines.len();
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y - 2, 34, (count + 3) as i32, white, black);
+    ctx.print_color(18, y - 2, yellow, black, "Character");
+    let keymap = gs.ecs.fetch::<Keymap>();
+    let prompt = format!("{} to close", keymap.key_label(Action::MenuCancel));
+    ctx.print_color(18, y + count as i32 + 1, yellow, black, &prompt);
+
+    for (j, line) in lines.iter().enumerate() {
+        ctx.print(17, y + j as i32, line);
+    }
+
+    let closing = keymap.action(ctx.key, Context::Menu) == Some(Action::MenuCancel)
+        || keymap.action(ctx.key, Context::Game) == Some(Action::CharacterSheet);
+    if closing {
+        ItemMenuResult::Cancel
+    } else {
+        ItemMenuResult::NoResponse
+    }
+}
+
+fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
+    let mouse_pos = ctx.mouse_pos();
+    let mouse_screen = Point::new(mouse_pos.0, mouse_pos.1);
+    let camera = Camera::new(ecs);
+
+    if let Some(mouse_world) = camera.screen_to_world(mouse_screen) {
+        describe_tile(ecs, ctx, mouse_world, mouse_screen);
+    }
+}
+
+pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk, selection: usize) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let stacks = carried_stacks(&gs.ecs, player_entity);
+    item_menu(&gs.ecs, ctx, "Drop which item?", &stacks, &[], selection)
+}
+
+pub fn show_trade(gs: &mut State, ctx: &mut Rltk, vendor: Entity, selling: bool, selection: usize) -> TradeResult {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let values = gs.ecs.read_storage::<Value>();
+
+    // vendors only deal in things with a price
+    let (title, stacks, prices) = if selling {
+        let stacks: Vec<ItemStack> = carried_stacks(&gs.ecs, player_entity)
+            .into_iter()
+            .filter(|stack| values.contains(stack.items[0]))
+            .collect();
+        let prices: Vec<i32> = stacks
+            .iter()
+            .map(|stack| trade::sell_price(values.get(stack.items[0]).unwrap().price))
+            .collect();
+        ("Sell", stacks, prices)
+    } else {
+        let stacks = carried_stacks(&gs.ecs, vendor);
+        let prices: Vec<i32> = stacks
+            .iter()
+            .map(|stack| values.get(stack.items[0]).map_or(0, |v| v.price))
+            .collect();
+        ("Buy", stacks, prices)
+    };
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names)
+    let title = format!("{}, {} credits", title, trade::credits(&gs.ecs, player_entity));
+    let result = item_menu(&gs.ecs, ctx, &title, &stacks, &prices, selection);
+
+    if gs.ecs.fetch::<Keymap>().action(ctx.key, Context::Menu) == Some(Action::SwitchTradeMode) {
+        return TradeResult::NoResponse { selling: !selling, selection: 0 };
+    }
+
+    match result {
+        (ItemMenuResult::Cancel, _) => TradeResult::Cancel,
+        (ItemMenuResult::Highlight { selection }, _) => TradeResult::NoResponse { selling, selection },
+        (ItemMenuResult::Selected, Some(item)) if selling => TradeResult::Sell(item),
+        (ItemMenuResult::Selected, Some(item)) => TradeResult::Buy(item),
+        _ => TradeResult::NoResponse { selling, selection },
+    }
+}
+
+/// Someone's backpack, stacked, under the names the player knows things by.
+fn carried_stacks(ecs: &World, owner: Entity) -> Vec<ItemStack> {
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackPack>();
+    let consumables = ecs.read_storage::<Consumable>();
+    let currency = ecs.read_storage::<Currency>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let identification = ecs.fetch::<Identification>();
+    let entities = ecs.entities();
+
+    let known_names: Vec<(Entity, String, bool)> = (&entities, &backpack, &names)
         .join()
-        .filter(|item| item.1.owner == *player_entity)
-    {
-        ctx.set(17, y, white, black, rltk::to_cp437('('));
-        ctx.set(17, y, yellow, black, 97 + j as u8);
-        ctx.set(19, y, white, black, rltk::to_cp437(')'));
+        .filter(|(_, pack, _)| pack.owner == owner)
+        .map(|(item, _, name)| {
+            let mut known_name = match currency.get(item) {
+                Some(money) => format!("{} {}", money.amount, name.name),
+                None => identification.name_of(&name.name),
+            };
+            if equipped.contains(item) {
+                known_name.push_str(" (wielded)");
+            }
+            (item, known_name, consumables.contains(item))
+        })
+        .collect();
+    stack_items(
+        known_names
+            .iter()
+            .map(|(item, name, consumable)| (*item, name.as_str(), *consumable)),
+    )
+}
+
+/// Lists stacks of items a page at a time, one letter per stack, with the
+/// highlighted stack described alongside. Picking a stack selects one item
+/// from it. Any prices given are shown against the matching stacks.
+fn item_menu(
+    ecs: &World,
+    ctx: &mut Rltk,
+    title: &str,
+    stacks: &[ItemStack],
+    prices: &[i32],
+    selection: usize,
+) -> (ItemMenuResult, Option<Entity>) {
+    let selection = usize::min(selection, stacks.len().saturating_sub(1));
+    let page = selection / ITEMS_PER_PAGE;
+    let pages = usize::max(1, stacks.len().div_ceil(ITEMS_PER_PAGE));
+    let first = page * ITEMS_PER_PAGE;
+    let shown = &stacks[first..usize::min(first + ITEMS_PER_PAGE, stacks.len())];
+    let count = shown.len();
+
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let yellow = RGB::named(rltk::YELLOW);
+    let magenta = RGB::named(rltk::MAGENTA);
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(8, y - 2, 38, (count + 3) as i32, white, black);
+    ctx.print_color(11, y - 2, yellow, black, title);
+    let prompt = format!("{} to cancel", ecs.fetch::<Keymap>().key_label(Action::MenuCancel));
+    ctx.print_color(11, y + count as i32 + 1, yellow, black, &prompt);
+    if pages > 1 {
+        ctx.print_color(38, y + count as i32 + 1, yellow, black, &format!("<{}/{}>", page + 1, pages));
+    }
+
+    for (j, stack) in shown.iter().enumerate() {
+        let y = y + j as i32;
+        ctx.set(10, y, white, black, rltk::to_cp437('('));
+        ctx.set(11, y, yellow, black, 97 + j as u8);
+        ctx.set(12, y, white, black, rltk::to_cp437(')'));
+
+        let colour = if first + j == selection { magenta } else { white };
+        if stack.items.len() > 1 {
+            ctx.print_color(14, y, colour, black, &format!("{} (x{})", stack.name, stack.items.len()));
+        } else {
+            ctx.print_color(14, y, colour, black, &stack.name);
+        }
+        if let Some(price) = prices.get(first + j) {
+            ctx.print_color(40, y, RGB::named(rltk::GOLD), black, &format!("{:>4}cr", price));
+        }
+    }
 
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        j += 1;
+    if let Some(stack) = stacks.get(selection) {
+        draw_description_panel(ctx, 48, y - 2, &description_lines(ecs, stack.items[0]));
     }
 
+    let last = stacks.len().saturating_sub(1);
+    let action = ecs.fetch::<Keymap>().action(ctx.key, Context::Menu);
     match ctx.key {
         None => (ItemMenuResult::NoResponse, None),
-        Some(key) => match key {
-            VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
+        Some(key) => match action {
+            Some(Action::MenuCancel) => (ItemMenuResult::Cancel, None),
+            Some(Action::MenuUp) if selection > 0 => {
+                (ItemMenuResult::Highlight { selection: selection - 1 }, None)
+            }
+            Some(Action::MenuDown) if selection < last => {
+                (ItemMenuResult::Highlight { selection: selection + 1 }, None)
+            }
+            Some(Action::MenuPageDown) if page + 1 < pages => {
+                let selection = usize::min(first + ITEMS_PER_PAGE, last);
+                (ItemMenuResult::Highlight { selection }, None)
+            }
+            Some(Action::MenuPageUp) if page > 0 => {
+                (ItemMenuResult::Highlight { selection: first - ITEMS_PER_PAGE }, None)
+            }
+            Some(Action::MenuSelect) if !stacks.is_empty() => {
+                (ItemMenuResult::Selected, Some(stacks[selection].items[0]))
+            }
             _ => {
                 let selection = rltk::letter_to_option(key);
                 if selection > -1 && selection < count as i32 {
                     return (
                         ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
+                        Some(shown[selection as usize].items[0]),
                     );
                 }
-
                 (ItemMenuResult::NoResponse, None)
             }
         },
     }
 }
 
-fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
-    let map = ecs.fetch::<Map>();
+/// Everything worth knowing about an entity as lines of coloured text: its
+/// name, its description, and what it does, worked out from its components.
+fn description_lines(ecs: &World, entity: Entity) -> Vec<(String, RGB)> {
     let names = ecs.read_storage::<Name>();
-    let positions = ecs.read_storage::<Position>();
-    let mouse_pos = ctx.mouse_pos();
-    let (map_width, map_height) = map.get_dimensions();
+    let descriptions = ecs.read_storage::<Description>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let healing = ecs.read_storage::<ProvidesHealing>();
+    let damage = ecs.read_storage::<InflictsDamage>();
+    let aoe = ecs.read_storage::<AreaOfEffect>();
+    let confusion = ecs.read_storage::<Confusion>();
+    let invigorated = ecs.read_storage::<Invigorated>();
+    let grants_invigoration = ecs.read_storage::<GrantsInvigoration>();
+    let reveals_map = ecs.read_storage::<RevealsMap>();
+    let detection = ecs.read_storage::<Detection>();
+    let grants_detection = ecs.read_storage::<GrantsDetection>();
+    let regenerates = ecs.read_storage::<Regenerates>();
+    let ranged = ecs.read_storage::<Ranged>();
+    let weapons = ecs.read_storage::<MeleeWeapon>();
+    let natural_attacks = ecs.read_storage::<NaturalAttack>();
+    let asleep = ecs.read_storage::<Asleep>();
+    let items = ecs.read_storage::<Item>();
+    let lights = ecs.read_storage::<LightSource>();
+    let unaware = ecs.read_storage::<Unaware>();
+    let identification = ecs.fetch::<Identification>();
 
-    if mouse_pos.0 >= map_width || mouse_pos.1 >= map_height {
-        return;
+    let white = RGB::named(rltk::WHITE);
+    let mut lines: Vec<(String, RGB)> = Vec::new();
+
+    if let Some(name) = names.get(entity) {
+        let colour = renderables.get(entity).map_or(white, |r| r.foreground);
+        lines.push((identification.name_of(&name.name), colour));
+
+        if !identification.is_identified(&name.name) {
+            lines.push(("You're not sure what it does.".to_string(), white));
+            return lines;
+        }
+    }
+    if let Some(description) = descriptions.get(entity) {
+        for line in wrap(&description.text, PANEL_TEXT_WIDTH) {
+            lines.push((line, white));
+        }
     }
 
-    let mut tooltip: Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 {
-            tooltip.push(name.name.to_string());
+    let mut effects: Vec<String> = Vec::new();
+    if let Some(stats) = combat_stats.get(entity) {
+        effects.push(format!("Health: {} / {}", stats.current_hp, stats.max_hp));
+    }
+    if asleep.get(entity).is_some() {
+        effects.push("Asleep".to_string());
+    } else if unaware.get(entity).is_some() {
+        effects.push("Hasn't noticed you".to_string());
+    }
+    if let Some(attack) = natural_attacks.get(entity) {
+        effects.push(format!("Attacks with its {} ({})", attack.name, dice_string(attack.damage)));
+    }
+    if items.contains(entity) {
+        if let Some(weapon) = weapons.get(entity) {
+            effects.push(format!("Damage: {}", dice_string(weapon.damage)));
+            if weapon.hit_bonus != 0 {
+                effects.push(format!("To hit: {:+}", weapon.hit_bonus));
+            }
+        }
+        if let Some(healing) = healing.get(entity) {
+            effects.push(format!("Heals {} HP", healing.heal_amount));
+        }
+        if let Some(damage) = damage.get(entity) {
+            effects.push(format!("Deals {} damage", damage.damage));
+        }
+        if let Some(aoe) = aoe.get(entity) {
+            effects.push(format!("Blast radius: {}", aoe.radius));
+        }
+        if let Some(confusion) = confusion.get(entity) {
+            effects.push(format!("Confuses for {} turns", confusion.turns));
+        }
+        if let Some(boost) = grants_invigoration.get(entity) {
+            effects.push(format!("Speeds healing for {} turns", boost.turns));
+        }
+        if reveals_map.get(entity).is_some() {
+            effects.push("Maps the whole ship".to_string());
+        }
+        if let Some(detecting) = grants_detection.get(entity) {
+            effects.push(format!("Detects creatures for {} turns", detecting.turns));
+        }
+        if let Some(ranged) = ranged.get(entity) {
+            effects.push(format!("Range: {}", ranged.range));
+        }
+    } else {
+        // on a creature these are statuses, not what it does
+        if let Some(confusion) = confusion.get(entity) {
+            effects.push(format!("Confused ({} turns)", confusion.turns));
+        }
+        if let Some(boost) = invigorated.get(entity) {
+            effects.push(format!("Invigorated ({} turns)", boost.turns));
+        }
+        if let Some(detecting) = detection.get(entity) {
+            effects.push(format!("Detecting ({} turns)", detecting.turns));
         }
     }
+    if regenerates.get(entity).is_some() {
+        effects.push("Regenerates".to_string());
+    }
+    if let Some(light) = lights.get(entity) {
+        effects.push(format!("Gives off light ({} tiles)", light.range));
+    }
+    if !effects.is_empty() {
+        lines.push((String::new(), white));
+        let cyan = RGB::named(rltk::CYAN);
+        lines.extend(effects.into_iter().map(|effect| (effect, cyan)));
+    }
 
-    let white: RGB = RGB::named(rltk::WHITE);
-    let grey: RGB = RGB::named(rltk::GREY);
+    lines
+}
 
-    if !tooltip.is_empty() {
-        let mut width = 0;
-        for s in tooltip.iter() {
-            if width < s.len() as i32 {
-                width = s.len() as i32;
-            }
+/// Breaks text into lines no longer than `width`, between words.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(line);
+            line = String::new();
         }
-        width += 3;
-
-        if mouse_pos.0 > 40 {
-            let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
-            let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
-                ctx.print_color(left_x, y, white, grey, s);
-                let padding = (width - s.len() as i32) - 1;
-                for i in 0..padding {
-                    ctx.print_color(arrow_pos.x - i, y, white, grey, &" ".to_string());
-                }
-                y += 1;
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draws a box around some description lines with its top left corner at
+/// `x`, `y`, nudged up if it would run off the bottom of the screen.
+fn draw_description_panel(ctx: &mut Rltk, x: i32, y: i32, lines: &[(String, RGB)]) {
+    let black = RGB::named(rltk::BLACK);
+    let height = lines.len() as i32 + 1;
+    let y = i32::max(0, i32::min(y, 49 - height));
+
+    ctx.draw_box(x, y, PANEL_TEXT_WIDTH as i32 + 1, height, RGB::named(rltk::WHITE), black);
+    for (i, (text, colour)) in lines.iter().enumerate() {
+        ctx.print_color(x + 1, y + 1 + i as i32, *colour, black, text);
+    }
+}
+
+/// Describes everything the player can see on a map tile in a panel beside
+/// the given screen position.
+fn describe_tile(ecs: &World, ctx: &mut Rltk, tile: Point, screen: Point) {
+    let map = ecs.fetch::<Map>();
+    if !map.tile_is_visible(tile.x, tile.y) {
+        return;
+    }
+
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let mut lines: Vec<(String, RGB)> = Vec::new();
+    for (entity, position) in (&entities, &positions).join() {
+        if position.x == tile.x && position.y == tile.y {
+            let description = description_lines(ecs, entity);
+            if description.is_empty() {
+                continue;
             }
-            ctx.print_color(arrow_pos.x, arrow_pos.y, white, grey, &"->".to_string())
-        } else {
-            let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
-            let left_x = mouse_pos.0 + 3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
-                ctx.print_color(left_x + 1, y, white, grey, s);
-                let padding = (width - s.len() as i32) - 1;
-                for i in 0..padding {
-                    ctx.print_color(arrow_pos.x + 1 + i, y, white, grey, &" ".to_string());
-                }
-                y += 1;
+            if !lines.is_empty() {
+                lines.push((String::new(), RGB::named(rltk::WHITE)));
             }
-            ctx.print_color(arrow_pos.x, arrow_pos.y, white, grey, &"<-".to_string())
+            lines.extend(description);
         }
     }
+
+    if !lines.is_empty() {
+        let x = if screen.x > 40 { screen.x - PANEL_TEXT_WIDTH as i32 - 3 } else { screen.x + 2 };
+        draw_description_panel(ctx, x, screen.y, &lines);
+    }
 }
 
-pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackPack>();
-    let entities = gs.ecs.entities();
+/// Highlights and describes the tile under the cursor, with a prompt at the
+/// top of the screen. Returns false if the cursor isn't on screen.
+fn draw_cursor(ecs: &World, ctx: &mut Rltk, camera: &Camera, cursor: Point, prompt: &str) -> bool {
+    let screen = match camera.world_to_screen(cursor) {
+        Some(screen) => screen,
+        None => return false,
+    };
 
-    let inventory = (&backpack, &names)
-        .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+    ctx.set_bg(screen.x, screen.y, RGB::named(rltk::MAGENTA));
+    ctx.print_color(5, 0, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), prompt);
+    describe_tile(ecs, ctx, cursor, screen);
+    true
+}
 
-    let white = RGB::named(rltk::WHITE);
-    let black = RGB::named(rltk::BLACK);
-    let yellow = RGB::named(rltk::YELLOW);
+/// Where the cursor ends up after a step. It can't wander off the visible
+/// part of the map.
+fn move_cursor(camera: &Camera, cursor: Point, delta: (i32, i32)) -> Point {
+    let moved = Point::new(cursor.x + delta.0, cursor.y + delta.1);
+    match camera.world_to_screen(moved).and_then(|screen| camera.screen_to_world(screen)) {
+        Some(_) => moved,
+        None => cursor,
+    }
+}
 
-    let mut y = (25 - (count / 2)) as i32;
-    ctx.draw_box(15, y - 2, 31, (count + 3) as i32, white, black);
-    ctx.print_color(18, y - 2, yellow, black, "Drop which item?");
-    ctx.print_color(18, y + count as i32 + 1, yellow, black, "ESCAPE to cancel");
+pub fn look_at(gs: &mut State, ctx: &mut Rltk, cursor: Point) -> LookResult {
+    let camera = Camera::new(&gs.ecs);
+    let prompt = format!("Look around. {} to stop.", gs.ecs.fetch::<Keymap>().key_label(Action::MenuCancel));
+    if !draw_cursor(&gs.ecs, ctx, &camera, cursor, &prompt) {
+        return LookResult::Cancel;
+    }
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names)
-        .join()
-        .filter(|item| item.1.owner == *player_entity)
-    {
-        ctx.set(17, 7, white, black, rltk::to_cp437('('));
-        ctx.set(18, y, yellow, black, 97 + j as u8);
-        ctx.set(19, 7, white, black, rltk::to_cp437(')'));
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        j += 1;
+    let keymap = gs.ecs.fetch::<Keymap>();
+    if keymap.action(ctx.key, Context::Menu) == Some(Action::MenuCancel) {
+        return LookResult::Cancel;
     }
+    let delta = match keymap.action(ctx.key, Context::Game) {
+        None => return LookResult::NoResponse { cursor },
+        Some(Action::LookAround) => return LookResult::Cancel,
+        Some(action) => action.direction().unwrap_or((0, 0)),
+    };
 
-    match ctx.key {
-        None => (ItemMenuResult::NoResponse, None),
-        Some(key) => match key {
-            VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-            _ => {
-                let selection = rltk::letter_to_option(key);
-                if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
+    LookResult::NoResponse { cursor: move_cursor(&camera, cursor, delta) }
+}
+
+pub fn choose_destination(gs: &mut State, ctx: &mut Rltk, cursor: Point) -> DestinationResult {
+    let camera = Camera::new(&gs.ecs);
+
+    // show the way there, if there is one
+    {
+        let map = gs.ecs.fetch::<Map>();
+        let player_pos = gs.ecs.fetch::<Point>();
+        if map.tile_is_revealed(cursor.x, cursor.y) {
+            let (width, _) = map.get_dimensions();
+            let path = rltk::a_star_search(
+                map.xy_idx(player_pos.x, player_pos.y),
+                map.xy_idx(cursor.x, cursor.y),
+                &*map,
+            );
+            if path.success {
+                for step in path.steps.iter().skip(1) {
+                    let tile = Point::new(*step as i32 % width, *step as i32 / width);
+                    if let Some(screen) = camera.world_to_screen(tile) {
+                        ctx.set_bg(screen.x, screen.y, RGB::named(rltk::DARK_CYAN));
+                    }
                 }
-                (ItemMenuResult::NoResponse, None)
             }
-        },
+        }
+    }
+
+    let prompt = {
+        let keymap = gs.ecs.fetch::<Keymap>();
+        format!(
+            "Travel where? {} to go, {} for the next item, {} to stop.",
+            keymap.key_label(Action::MenuSelect),
+            keymap.key_label(Action::NextDestination),
+            keymap.key_label(Action::MenuCancel)
+        )
+    };
+    if !draw_cursor(&gs.ecs, ctx, &camera, cursor, &prompt) {
+        return DestinationResult::Cancel;
+    }
+
+    if ctx.left_click {
+        let mouse_pos = ctx.mouse_pos();
+        if let Some(destination) = camera.screen_to_world(Point::new(mouse_pos.0, mouse_pos.1)) {
+            return DestinationResult::Selected { destination };
+        }
+    }
+
+    let keymap = gs.ecs.fetch::<Keymap>();
+    match keymap.action(ctx.key, Context::Menu) {
+        Some(Action::MenuCancel) => return DestinationResult::Cancel,
+        Some(Action::MenuSelect) => return DestinationResult::Selected { destination: cursor },
+        Some(Action::NextDestination) => {
+            return DestinationResult::NoResponse {
+                cursor: next_known_item(&gs.ecs, cursor).unwrap_or(cursor),
+            }
+        }
+        _ => {}
+    }
+    let delta = match keymap.action(ctx.key, Context::Game) {
+        None => return DestinationResult::NoResponse { cursor },
+        Some(Action::Travel) => return DestinationResult::Selected { destination: cursor },
+        Some(action) => action.direction().unwrap_or((0, 0)),
+    };
+
+    DestinationResult::NoResponse { cursor: move_cursor(&camera, cursor, delta) }
+}
+
+/// The next item the player knows about, nearest first, after the one under
+/// the cursor.
+fn next_known_item(ecs: &World, cursor: Point) -> Option<Point> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let camera = Camera::new(ecs);
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+
+    let mut known: Vec<Point> = (&items, &positions)
+        .join()
+        .map(|(_, pos)| Point::new(pos.x, pos.y))
+        .filter(|pos| map.tile_is_revealed(pos.x, pos.y) && camera.world_to_screen(*pos).is_some())
+        .collect();
+    known.sort_by_key(|pos| {
+        let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *pos);
+        ((distance * 100.0) as i32, pos.y, pos.x)
+    });
+    known.dedup();
+
+    match known.iter().position(|pos| *pos == cursor) {
+        Some(current) => known.get((current + 1) % known.len()).copied(),
+        None => known.first().copied(),
     }
 }
 
@@ -308,6 +878,8 @@ pub fn ranged_target(
     let player_entity = gs.ecs.fetch::<Entity>();
     let player_pos = gs.ecs.fetch::<Point>();
     let viewsheds = gs.ecs.read_storage::<ViewShed>();
+    let map = gs.ecs.fetch::<Map>();
+    let camera = Camera::new(&gs.ecs);
 
     let yellow = RGB::named(rltk::YELLOW);
     let black = RGB::named(rltk::BLACK);
@@ -321,11 +893,13 @@ pub fn ranged_target(
     let mut available_cells = Vec::new();
     let visible = viewsheds.get(*player_entity);
     if let Some(visible) = visible {
-        // we have a viewshed
-        for idx in visible.visible_tiles.iter() {
+        // we have a viewshed, but nothing in the dark can be picked out
+        for idx in visible.visible_tiles.iter().filter(|p| map.tile_is_visible(p.x, p.y)) {
             let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, blue);
+                if let Some(screen) = camera.world_to_screen(*idx) {
+                    ctx.set_bg(screen.x, screen.y, blue);
+                }
                 available_cells.push(idx);
             }
         }
@@ -335,20 +909,20 @@ pub fn ranged_target(
 
     // draw mouse cursor
     let mouse_pos = ctx.mouse_pos();
+    let mouse_world = camera.screen_to_world(Point::new(mouse_pos.0, mouse_pos.1));
     let mut valid_target = false;
-    for idx in available_cells.iter() {
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
-            valid_target = true;
+    if let Some(mouse_world) = mouse_world {
+        for idx in available_cells.iter() {
+            if idx.x == mouse_world.x && idx.y == mouse_world.y {
+                valid_target = true;
+            }
         }
     }
 
     if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, cyan);
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            return (ItemMenuResult::Selected, mouse_world);
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, red);
@@ -357,5 +931,108 @@ pub fn ranged_target(
         }
     }
 
+    if gs.ecs.fetch::<Keymap>().action(ctx.key, Context::Menu) == Some(Action::MenuCancel) {
+        return (ItemMenuResult::Cancel, None);
+    }
+
     (ItemMenuResult::NoResponse, None)
 }
+
+/// Prints the segments of a log entry one after another, cut off at `max_x`.
+fn print_log_entry(ctx: &mut Rltk, x: i32, y: i32, max_x: i32, entry: &LogEntry) {
+    let black = RGB::named(rltk::BLACK);
+
+    let mut x = x;
+    for segment in entry.segments.iter() {
+        for c in segment.text.chars() {
+            if x >= max_x {
+                return;
+            }
+            ctx.set(x, y, segment.colour, black, rltk::to_cp437(c));
+            x += 1;
+        }
+    }
+}
+
+pub fn show_log(gs: &mut State, ctx: &mut Rltk, offset: usize) -> LogScreenResult {
+    let log = gs.ecs.fetch::<GameLog>();
+
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let yellow = RGB::named(rltk::YELLOW);
+    let grey = RGB::named(rltk::GREY);
+
+    const PAGE: usize = 46;
+    let last_offset = log.entries.len().saturating_sub(PAGE);
+    let offset = usize::min(offset, last_offset);
+
+    ctx.draw_box(0, 0, 79, 49, white, black);
+    ctx.print_color(3, 0, yellow, black, "Message History");
+    let keymap = gs.ecs.fetch::<Keymap>();
+    let prompt = format!(
+        "{}/{}, {}/{} to scroll, {} to close",
+        keymap.key_label(Action::MenuUp),
+        keymap.key_label(Action::MenuDown),
+        keymap.key_label(Action::MenuPageUp),
+        keymap.key_label(Action::MenuPageDown),
+        keymap.key_label(Action::MenuCancel)
+    );
+    ctx.print_color(3, 49, yellow, black, &prompt);
+
+    for (y, entry) in (2..).zip(log.entries.iter().rev().skip(offset).take(PAGE)) {
+        ctx.print_color(2, y, grey, black, &format!("{:>5}", entry.turn));
+        print_log_entry(ctx, 9, y, 78, entry);
+    }
+
+    // nothing on this screen is picked by letter, so the movement keys
+    // scroll it as well
+    let action = keymap
+        .action(ctx.key, Context::Menu)
+        .or_else(|| keymap.action(ctx.key, Context::Game));
+    match action {
+        Some(Action::MenuCancel) | Some(Action::ShowLog) => LogScreenResult::Cancel,
+        Some(Action::MenuUp) | Some(Action::MoveN) => LogScreenResult::NoResponse {
+            offset: offset.saturating_sub(1),
+        },
+        Some(Action::MenuDown) | Some(Action::MoveS) => LogScreenResult::NoResponse {
+            offset: usize::min(offset + 1, last_offset),
+        },
+        Some(Action::MenuPageUp) => LogScreenResult::NoResponse {
+            offset: offset.saturating_sub(PAGE),
+        },
+        Some(Action::MenuPageDown) => LogScreenResult::NoResponse {
+            offset: usize::min(offset + PAGE, last_offset),
+        },
+        _ => LogScreenResult::NoResponse { offset },
+    }
+}
+
+pub fn show_bindings(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
+    let keymap = gs.ecs.fetch::<Keymap>();
+
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let yellow = RGB::named(rltk::YELLOW);
+    let cyan = RGB::named(rltk::CYAN);
+
+    let count = Action::ALL.len() as i32;
+    let y = 25 - count / 2;
+    ctx.draw_box(10, y - 2, 59, count + 3, white, black);
+    ctx.print_color(13, y - 2, yellow, black, "Key Bindings");
+    ctx.print_color(13, y + count + 1, yellow, black, &format!("{} to close", keymap.key_label(Action::MenuCancel)));
+
+    for (j, action) in Action::ALL.iter().enumerate() {
+        let y = y + j as i32;
+        let keys: Vec<String> = keymap.keys_for(*action).iter().map(|key| key_name(*key)).collect();
+        ctx.print_color(12, y, white, black, action.describe());
+        ctx.print_color(34, y, cyan, black, &keys.join(", "));
+    }
+
+    let closing = keymap.action(ctx.key, Context::Menu) == Some(Action::MenuCancel)
+        || keymap.action(ctx.key, Context::Game) == Some(Action::ShowBindings);
+    if closing {
+        ItemMenuResult::Cancel
+    } else {
+        ItemMenuResult::NoResponse
+    }
+}
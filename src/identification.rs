@@ -0,0 +1,106 @@
+use rltk::{RandomNumberGenerator, RGB};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const VIAL: usize = 0;
+const DATACHIP: usize = 1;
+
+// a description, and the colour it is drawn in
+type Appearance = (&'static str, (u8, u8, u8));
+
+// what each kind of disguised item might look like, and its colour, so that
+// the colour gives away no more than the name does
+const APPEARANCES: [&[Appearance]; 2] = [
+    &[
+        ("blue vial", rltk::BLUE),
+        ("fizzing green vial", rltk::GREEN),
+        ("murky vial", rltk::OLIVE),
+        ("glowing amber vial", rltk::ORANGE),
+        ("silver vial", rltk::SILVER),
+        ("smoking black vial", rltk::DIMGREY),
+    ],
+    &[
+        ("crackling datachip", rltk::CYAN),
+        ("scratched datachip", rltk::LIGHTGREY),
+        ("humming datachip", rltk::LIGHTBLUE),
+        ("bent datachip", rltk::PURPLE),
+        ("gold-plated datachip", rltk::GOLD),
+        ("warm datachip", rltk::ORANGE_RED),
+        ("flickering datachip", rltk::MAGENTA),
+    ],
+];
+
+// item types that have to be identified, and the kind of thing they look like
+const DISGUISED: &[(&str, usize)] = &[
+    ("Health Potion", VIAL),
+    ("Scroll of Magic Missle", DATACHIP),
+    ("Scroll of Fireball", DATACHIP),
+    ("Scroll of Confusion", DATACHIP),
+    ("Scroll of Identify", DATACHIP),
+];
+
+/// What each kind of item looks like this run, and which kinds the player
+/// has worked out. Items are looked up by their real name.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Identification {
+    appearances: HashMap<String, String>,
+    identified: HashSet<String>,
+}
+
+impl Identification {
+    pub fn new(rng: &mut RandomNumberGenerator) -> Identification {
+        // no two item types may look alike
+        let mut unused: Vec<Vec<&str>> = APPEARANCES
+            .iter()
+            .map(|pool| pool.iter().map(|(appearance, _)| *appearance).collect())
+            .collect();
+        let mut appearances = HashMap::new();
+
+        for (name, kind) in DISGUISED.iter() {
+            let left = &mut unused[*kind];
+            let pick = rng.range(0, left.len() as i32) as usize;
+            appearances.insert(name.to_string(), left.remove(pick).to_string());
+        }
+
+        Identification {
+            appearances,
+            identified: HashSet::new(),
+        }
+    }
+
+    /// The name the player knows an item by.
+    pub fn name_of(&self, real_name: &str) -> String {
+        match self.appearances.get(real_name) {
+            Some(appearance) if !self.is_identified(real_name) => appearance.to_string(),
+            _ => real_name.to_string(),
+        }
+    }
+
+    /// The colour an item type is drawn in this run. Disguised items take the
+    /// colour of what they look like, identified or not; anything else keeps
+    /// `default`.
+    pub fn colour_of(&self, real_name: &str, default: RGB) -> RGB {
+        let appearance = match self.appearances.get(real_name) {
+            Some(appearance) => appearance,
+            None => return default,
+        };
+        APPEARANCES
+            .iter()
+            .flat_map(|pool| pool.iter())
+            .find(|(name, _)| name == appearance)
+            .map_or(default, |(_, colour)| RGB::named(*colour))
+    }
+
+    pub fn is_identified(&self, real_name: &str) -> bool {
+        !self.appearances.contains_key(real_name) || self.identified.contains(real_name)
+    }
+
+    /// Reveals an item type's real name for the rest of the run. Returns
+    /// whether it was news.
+    pub fn identify(&mut self, real_name: &str) -> bool {
+        if self.is_identified(real_name) {
+            return false;
+        }
+        self.identified.insert(real_name.to_string())
+    }
+}
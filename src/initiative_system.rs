@@ -0,0 +1,53 @@
+use specs::prelude::*;
+use super::*;
+
+/// Energy gained per tick by an entity of ordinary speed.
+pub const NORMAL_SPEED: i32 = 10;
+
+/// Energy an entity must build up, and then spends, to take an action.
+pub const TURN_COST: i32 = 100;
+
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        WriteStorage<'a, Initiative>,
+                        WriteStorage<'a, MyTurn>,
+                        ReadExpect<'a, Entity>,
+                        WriteExpect<'a, RunState>,
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut initiatives, mut turns, player_entity, mut runstate) = data;
+
+        // whoever had a turn has now taken it
+        turns.clear();
+
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        // nobody can ever act, so hand control back rather than spin forever
+        if (&initiatives).join().all(|initiative| initiative.speed < 1) {
+            *runstate = RunState::AwaitingInput;
+            return;
+        }
+
+        let mut someone_is_ready = false;
+        while !someone_is_ready {
+            for (entity, initiative) in (&entities, &mut initiatives).join() {
+                initiative.energy += initiative.speed;
+                if initiative.energy >= TURN_COST {
+                    initiative.energy -= TURN_COST;
+                    turns.insert(entity, MyTurn {}).expect("Unable to insert turn");
+                    someone_is_ready = true;
+                }
+            }
+        }
+
+        if turns.get(*player_entity).is_some() {
+            *runstate = RunState::AwaitingInput;
+        }
+    }
+}
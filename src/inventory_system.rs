@@ -1,5 +1,8 @@
 use specs::prelude::*;
+use rltk::RGB;
 use super::*;
+use particle_system::{ParticleBuilder, FLASH_MS};
+use awareness_system::{Noises, EXPLOSION_NOISE};
 
 pub struct ItemUseSystem {}
 
@@ -17,7 +20,21 @@ impl<'a> System<'a> for ItemUseSystem {
                         WriteStorage<'a, SufferDamage>,
                         ReadStorage<'a, AreaOfEffect>,
                         WriteStorage<'a, Confusion>,
+                        WriteStorage<'a, Invigorated>,
+                        ReadStorage<'a, GrantsInvigoration>,
                         ReadExpect<'a, Map>,
+                        ReadStorage<'a, Renderable>,
+                        WriteExpect<'a, Identification>,
+                        ReadStorage<'a, Identifies>,
+                        ReadStorage<'a, InBackPack>,
+                        ReadStorage<'a, Position>,
+                        WriteExpect<'a, ParticleBuilder>,
+                        ReadStorage<'a, MeleeWeapon>,
+                        WriteStorage<'a, Equipped>,
+                        ReadStorage<'a, TechItem>,
+                        ReadStorage<'a, Ranged>,
+                        // specs stops at 26 elements, so the rest come together
+                        (WriteStorage<'a, Skills>, WriteExpect<'a, Noises>, ReadStorage<'a, RevealsMap>, WriteStorage<'a, Detection>, ReadStorage<'a, GrantsDetection>, WriteExpect<'a, RunState>),
                     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -25,7 +42,7 @@ impl<'a> System<'a> for ItemUseSystem {
             player_entity, 
             mut gamelog, 
             entities, 
-            use_item, 
+            mut wants_use,
             names, 
             consumables,
             healing,
@@ -34,29 +51,127 @@ impl<'a> System<'a> for ItemUseSystem {
             mut suffer_damage,
             aoe,
             mut confused,
-            map
+            mut invigorated,
+            grants_invigoration,
+            map,
+            renderables,
+            mut identification,
+            identifies,
+            backpack,
+            positions,
+            mut particles,
+            weapons,
+            mut equipped,
+            tech_items,
+            ranged,
+            (mut skills, mut noises, reveals_map, mut detection, grants_detection, mut runstate),
         ) = data;
 
-        for (entity, use_item) in (&entities, &use_item).join() {
+        for (entity, use_item) in (&entities, &wants_use).join() {
+            // the item may have been used up, or destroyed, since it was picked
+            let real_name = match names.get(use_item.item) {
+                Some(name) => &name.name,
+                None => continue,
+            };
+            let item_colour = renderables.get(use_item.item).map_or(RGB::named(rltk::WHITE), |r| r.foreground);
+
+            // only tell the player about things they could have seen
+            let is_player = entity == *player_entity;
+            let seen = is_player || positions.get(entity).is_some_and(|pos| map.tile_is_visible(pos.x, pos.y));
+            let user_name = if is_player { "You".to_string() } else { format!("The {}", names.get(entity).unwrap().name) };
+            let name_of = |mob: Entity| if mob == *player_entity { "you".to_string() } else { names.get(mob).unwrap().name.to_string() };
+
+            // using something is a sure way of finding out what it is
+            if entity == *player_entity {
+                let appearance = identification.name_of(real_name);
+                if identification.identify(real_name) {
+                    gamelog.entry()
+                        .text("The ")
+                        .coloured(&appearance, item_colour)
+                        .text(" is a ")
+                        .coloured(real_name, item_colour)
+                        .text("!")
+                        .log();
+                }
+            }
+            let item_name = identification.name_of(real_name);
+
+            // tech does more in the hands of someone who knows how to use it
+            let is_tech = tech_items.contains(use_item.item);
+            let tech_bonus = if is_tech { skills.get(entity).map_or(0, |s| s.level(Skill::Tech)) } else { 0 };
+
+            // IDENTIFICATION
+            if identifies.get(use_item.item).is_some() {
+                for (item, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == entity) {
+                    let appearance = identification.name_of(&name.name);
+                    if identification.identify(&name.name) && entity == *player_entity {
+                        let colour = renderables.get(item).map_or(RGB::named(rltk::WHITE), |r| r.foreground);
+                        gamelog.entry()
+                            .text("The ")
+                            .coloured(&appearance, colour)
+                            .text(" is a ")
+                            .coloured(&name.name, colour)
+                            .text("!")
+                            .log();
+                    }
+                }
+            }
+
+            // WIELDING
+            if weapons.get(use_item.item).is_some() {
+                let wielding = equipped.get(use_item.item).is_some_and(|e| e.owner == entity);
+                // only one weapon in hand at a time
+                let in_hand: Vec<Entity> = (&entities, &equipped, &weapons)
+                    .join()
+                    .filter(|(_, e, _)| e.owner == entity)
+                    .map(|(item, _, _)| item)
+                    .collect();
+                for item in in_hand.iter() {
+                    equipped.remove(*item);
+                }
+                if !wielding {
+                    equipped.insert(use_item.item, Equipped{ owner: entity }).expect("Unable to equip weapon");
+                }
+                if seen {
+                    let verb = match (wielding, is_player) {
+                        (true, true) => "put away",
+                        (true, false) => "puts away",
+                        (false, true) => "wield",
+                        (false, false) => "wields",
+                    };
+                    gamelog.entry()
+                        .text(format!("{} {} the ", user_name, verb))
+                        .coloured(&item_name, item_colour)
+                        .text(".")
+                        .log();
+                }
+            }
+
             // TARGETING
             let mut targets: Vec<Entity> = Vec::new();
             match use_item.target {
-                None => { targets.push(*player_entity); }
+                None => { targets.push(entity); }
                 Some(target) => {
                     let area_effect = aoe.get(use_item.item);
                     match area_effect {
                         None => {
                             for mob in map.get_tile_content(target.x, target.y).iter () {
-                                targets.push(*mob);
+                                if combat_stats.contains(*mob) {
+                                    targets.push(*mob);
+                                }
                             }
                         }
                         Some(area_effect) => {
                             let mut blast_tiles = rltk::field_of_view(target, area_effect.radius, &*map);
                             let (map_width, map_height) = map.get_dimensions();
                             blast_tiles.retain(|p| p.x > 0 && p.x < map_width-1 && p.y > 0 && p.y < map_height -1);
+                            noises.make(entity, target, EXPLOSION_NOISE);
                             for tile in blast_tiles.iter() {
+                                particles.request(tile.x, tile.y, RGB::named(rltk::ORANGE), RGB::named(rltk::BLACK), rltk::to_cp437('░'), FLASH_MS);
                                 for mob in map.get_tile_content(tile.x, tile.y).iter() {
-                                    targets.push(*mob);
+                                    if combat_stats.contains(*mob) {
+                                        targets.push(*mob);
+                                    }
                                 }
                             }
                         }
@@ -73,8 +188,16 @@ impl<'a> System<'a> for ItemUseSystem {
                         let stats = combat_stats.get_mut(*target);
                         if let Some(stats) = stats {
                             stats.current_hp = i32::min(stats.max_hp, stats.current_hp + healer.heal_amount);
-                            if entity == *player_entity {
-                                gamelog.entries.push(format!("You drink the {}, regaining {} hp", names.get(use_item.item).unwrap().name, healer.heal_amount));
+                            if let Some(pos) = positions.get(*target) {
+                                particles.request(pos.x, pos.y, RGB::named(rltk::GREEN), RGB::named(rltk::BLACK), rltk::to_cp437('♥'), FLASH_MS);
+                            }
+                            if seen {
+                                gamelog.entry()
+                                    .text(format!("{} {} the ", user_name, if is_player { "drink" } else { "drinks" }))
+                                    .coloured(&item_name, item_colour)
+                                    .text(", regaining ")
+                                    .coloured(format!("{} hp", healer.heal_amount), RGB::named(rltk::GREEN))
+                                    .log();
                             }
                         }
                     }
@@ -86,12 +209,20 @@ impl<'a> System<'a> for ItemUseSystem {
             match item_damages {
                 None => {}
                 Some(damage) => {
+                    let amount = damage.damage + tech_bonus;
                     for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
-                        if entity == *player_entity {
-                            let mob_name = names.get(*mob).unwrap();
-                            let item_name = names.get(use_item.item).unwrap();
-                            gamelog.entries.push(format!("You use {} on {}, inflicting {} hp of damage.", item_name.name, mob_name.name, damage.damage));
+                        SufferDamage::new_damage(&mut suffer_damage, *mob, amount, entity == *player_entity);
+                        if let Some(pos) = positions.get(*mob) {
+                            particles.request(pos.x, pos.y, RGB::named(rltk::RED), RGB::named(rltk::BLACK), rltk::to_cp437('‼'), FLASH_MS);
+                        }
+                        if seen {
+                            gamelog.entry()
+                                .text(format!("{} {} the ", user_name, if is_player { "use" } else { "uses" }))
+                                .coloured(&item_name, item_colour)
+                                .text(format!(" on {}, inflicting ", name_of(*mob)))
+                                .coloured(format!("{} hp", amount), RGB::named(rltk::RED))
+                                .text(" of damage.")
+                                .log();
                         }
                     }
                 }
@@ -105,11 +236,18 @@ impl<'a> System<'a> for ItemUseSystem {
                     None => {}
                     Some(confusion) => {
                         for mob in targets.iter() {
-                            add_confusion.push((*mob, confusion.turns));
-                            if entity == *player_entity {
-                                let mob_name = names.get(*mob).unwrap();
-                                let item_name = names.get(use_item.item).unwrap();
-                                gamelog.entries.push(format!("You use {} on {}, confusing them.", item_name.name, mob_name.name));
+                            add_confusion.push((*mob, confusion.turns + tech_bonus));
+                            if let Some(pos) = positions.get(*mob) {
+                                particles.request(pos.x, pos.y, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), rltk::to_cp437('?'), FLASH_MS);
+                            }
+                            if seen {
+                                gamelog.entry()
+                                    .text(format!("{} {} the ", user_name, if is_player { "use" } else { "uses" }))
+                                    .coloured(&item_name, item_colour)
+                                    .text(format!(" on {}, ", name_of(*mob)))
+                                    .coloured("confusing them", RGB::named(rltk::MAGENTA))
+                                    .text(".")
+                                    .log();
                             }
                         }
                     }
@@ -119,6 +257,67 @@ impl<'a> System<'a> for ItemUseSystem {
                 confused.insert(mob.0, Confusion{ turns: mob.1 }).expect("Unable to insert status");
             }
 
+            // INVIGORATION
+            let mut add_invigoration = Vec::new();
+            if let Some(boost) = grants_invigoration.get(use_item.item) {
+                for target in targets.iter() {
+                    add_invigoration.push((*target, boost.turns));
+                    if let Some(pos) = positions.get(*target) {
+                        particles.request(pos.x, pos.y, RGB::named(rltk::GREEN), RGB::named(rltk::BLACK), rltk::to_cp437('+'), FLASH_MS);
+                    }
+                    if seen {
+                        gamelog.entry()
+                            .text(format!("{} {} the ", user_name, if is_player { "inject" } else { "injects" }))
+                            .coloured(&item_name, item_colour)
+                            .text(", feeling ")
+                            .coloured("invigorated", RGB::named(rltk::GREEN))
+                            .text(".")
+                            .log();
+                    }
+                }
+            }
+            for target in add_invigoration.iter() {
+                invigorated.insert(target.0, Invigorated{ turns: target.1 }).expect("Unable to insert status");
+            }
+
+            // MAPPING
+            if reveals_map.contains(use_item.item) && is_player {
+                gamelog.entry()
+                    .text("You study the ")
+                    .coloured(&item_name, item_colour)
+                    .text(", and the layout of the ship falls into place.")
+                    .log();
+                *runstate = RunState::MagicMapReveal { row: 0 };
+            }
+
+            // DETECTION
+            let mut add_detection = Vec::new();
+            if let Some(detecting) = grants_detection.get(use_item.item) {
+                for target in targets.iter() {
+                    add_detection.push((*target, detecting.turns + tech_bonus));
+                    if seen {
+                        gamelog.entry()
+                            .text(format!("{} {} on the ", user_name, if is_player { "switch" } else { "switches" }))
+                            .coloured(&item_name, item_colour)
+                            .text(", and it picks up ")
+                            .coloured("movement", RGB::named(rltk::MAGENTA))
+                            .text(" all over the ship.")
+                            .log();
+                    }
+                }
+            }
+            for target in add_detection.iter() {
+                detection.insert(target.0, Detection{ turns: target.1 }).expect("Unable to insert status");
+            }
+
+            // PRACTICE
+            if is_tech {
+                Skills::practise_for(&mut skills, &mut gamelog, entity, is_player, Skill::Tech);
+            }
+            if use_item.target.is_some() && ranged.contains(use_item.item) {
+                Skills::practise_for(&mut skills, &mut gamelog, entity, is_player, Skill::Ranged);
+            }
+
             // CLEANUP
             let consumable = consumables.get(use_item.item);
             match consumable {
@@ -128,6 +327,8 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
         }
+
+        wants_use.clear();
     }
 }
 
@@ -141,7 +342,10 @@ impl<'a> System<'a> for ItemDropSystem {
                         WriteStorage<'a, WantsToDropItem>,
                         ReadStorage<'a, Name>,
                         WriteStorage<'a, Position>,
-                        WriteStorage<'a, InBackPack>
+                        WriteStorage<'a, InBackPack>,
+                        ReadStorage<'a, Renderable>,
+                        ReadExpect<'a, Identification>,
+                        WriteStorage<'a, Equipped>,
                     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -152,7 +356,10 @@ impl<'a> System<'a> for ItemDropSystem {
             mut wants_drop,
             names,
             mut positions,
-            mut backpack
+            mut backpack,
+            renderables,
+            identification,
+            mut equipped
         ) = data;
 
         for (entity, to_drop) in (&entites, &wants_drop).join() {
@@ -165,12 +372,82 @@ impl<'a> System<'a> for ItemDropSystem {
 
             positions.insert(to_drop.item, dropper_pos).expect("Unable to insert position");
             backpack.remove(to_drop.item);
+            equipped.remove(to_drop.item);
 
             if entity == *player_entity {
-                gamelog.entries.push(format!("You drop the {}", names.get(to_drop.item).unwrap().name));
+                let colour = renderables.get(to_drop.item).map_or(RGB::named(rltk::WHITE), |r| r.foreground);
+                gamelog.entry()
+                    .text("You drop the ")
+                    .coloured(identification.name_of(&names.get(to_drop.item).unwrap().name), colour)
+                    .text(".")
+                    .log();
             }
         }
 
         wants_drop.clear();
     }
-}
\ No newline at end of file
+}
+
+// how many different kinds of thing a backpack can hold
+pub const BACKPACK_SLOTS: usize = 30;
+
+/// A pile of items that take up a single backpack slot.
+pub struct ItemStack {
+    pub name: String,
+    pub items: Vec<Entity>,
+    stackable: bool,
+}
+
+/// Groups a backpack's contents into stacks. Identical consumables share a
+/// stack; anything else gets a stack of its own.
+pub fn stack_items<'a>(contents: impl Iterator<Item = (Entity, &'a str, bool)>) -> Vec<ItemStack> {
+    let mut stacks: Vec<ItemStack> = Vec::new();
+    for (item, name, consumable) in contents {
+        match stacks.iter_mut().find(|s| consumable && s.stackable && s.name == name) {
+            Some(stack) => stack.items.push(item),
+            None => stacks.push(ItemStack { name: name.to_string(), items: vec![item], stackable: consumable }),
+        }
+    }
+    stacks
+}
+
+/// Whether an item can go into a backpack without needing a new slot, or
+/// there's a free slot for it.
+pub fn has_room_for(stacks: &[ItemStack], name: &str, consumable: bool) -> bool {
+    stacks.len() < BACKPACK_SLOTS || (consumable && stacks.iter().any(|s| s.stackable && s.name == name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_used_up_item_is_only_used_once() {
+        let mut world = World::new();
+        System::setup(&mut ItemUseSystem {}, &mut world);
+        world.insert(GameLog::default());
+        world.insert(Map::new(20, 20));
+        world.insert(Identification::default());
+        world.insert(ParticleBuilder::default());
+        world.insert(Noises::default());
+        world.insert(RunState::PlayerTurn);
+        let player = world.create_entity().build();
+        world.insert(player);
+
+        let medkit = world.create_entity()
+            .with(Name { name: "Medkit".to_string() })
+            .with(Consumable {})
+            .with(ProvidesHealing { heal_amount: 8 })
+            .build();
+        world.write_storage::<WantsToUseItem>()
+            .insert(player, WantsToUseItem { item: medkit, target: None })
+            .expect("Unable to insert intent");
+
+        // the second run would trip over the medkit it deleted in the first
+        for _run in 0..2 {
+            ItemUseSystem {}.run_now(&world);
+            world.maintain();
+        }
+        assert!(world.read_storage::<WantsToUseItem>().is_empty());
+    }
+}
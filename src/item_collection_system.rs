@@ -1,27 +1,88 @@
 use specs::prelude::*;
-use super::{WantsToPickupItem, Name, InBackPack, Position, GameLog};
+use rltk::{Point, RGB};
+use super::{WantsToPickupItem, Name, InBackPack, Position, GameLog, Renderable, Consumable, Identification, Map, Currency};
+use super::inventory_system::{stack_items, has_room_for};
 
 pub struct ItemCollectionSystem {}
 
 impl<'a> System<'a> for ItemCollectionSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
+        Entities<'a>,
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
-        WriteStorage<'a, InBackPack>
+        WriteStorage<'a, InBackPack>,
+        ReadStorage<'a, Renderable>,
+        ReadStorage<'a, Consumable>,
+        ReadExpect<'a, Identification>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, Currency>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) = data;
+        let (entities, player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack, renderables, consumables, identification, map, mut currency) = data;
 
         for pickup in wants_pickup.join() {
+            // someone else got there first
+            let item_pos = match positions.get(pickup.item) {
+                Some(pos) => Point::new(pos.x, pos.y),
+                None => continue,
+            };
+
+            // money goes straight into whatever money they already have
+            if let Some(amount) = currency.get(pickup.item).map(|c| c.amount) {
+                let purse = (&entities, &backpack, &currency)
+                    .join()
+                    .find(|(_, pack, _)| pack.owner == pickup.collected_by)
+                    .map(|(purse, _, _)| purse);
+                if let Some(purse) = purse {
+                    currency.get_mut(purse).unwrap().amount += amount;
+                    positions.remove(pickup.item);
+                    entities.delete(pickup.item).expect("Unable to delete credits");
+                    if pickup.collected_by == *player_entity {
+                        gamelog.entry()
+                            .text("You pick up ")
+                            .coloured(format!("{} credits", amount), RGB::named(rltk::GOLD))
+                            .text(".")
+                            .log();
+                    }
+                    continue;
+                }
+            }
+
+            let stacks = stack_items(
+                (&entities, &backpack, &names)
+                    .join()
+                    .filter(|(_, pack, _)| pack.owner == pickup.collected_by)
+                    .map(|(item, _, name)| (item, name.name.as_str(), consumables.contains(item))),
+            );
+            let item_name = &names.get(pickup.item).unwrap().name;
+            if !has_room_for(&stacks, item_name, consumables.contains(pickup.item)) {
+                if pickup.collected_by == *player_entity {
+                    gamelog.log("Your backpack is full.");
+                }
+                continue;
+            }
+
             positions.remove(pickup.item);
             backpack.insert(pickup.item, InBackPack{ owner: pickup.collected_by }).expect("Unable to insert backpack entry");
 
+            let colour = renderables.get(pickup.item).map_or(RGB::named(rltk::WHITE), |r| r.foreground);
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!("You picked up the {}.", names.get(pickup.item).unwrap().name));
+                gamelog.entry()
+                    .text("You picked up the ")
+                    .coloured(identification.name_of(item_name), colour)
+                    .text(".")
+                    .log();
+            } else if map.tile_is_visible(item_pos.x, item_pos.y) {
+                gamelog.entry()
+                    .text(format!("The {} picks up the ", names.get(pickup.collected_by).unwrap().name))
+                    .coloured(identification.name_of(item_name), colour)
+                    .text(".")
+                    .log();
             }
         }
 
@@ -0,0 +1,433 @@
+use rltk::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const KEYMAP_PATH: &str = "./keymap.json";
+
+/// Everything a key can be bound to.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Action {
+    MoveN,
+    MoveS,
+    MoveE,
+    MoveW,
+    MoveNE,
+    MoveNW,
+    MoveSE,
+    MoveSW,
+    PickUp,
+    Inventory,
+    Drop,
+    ShowLog,
+    CharacterSheet,
+    LookAround,
+    Wait,
+    Rest,
+    Explore,
+    Travel,
+    ShowBindings,
+    SaveAndQuit,
+    MenuUp,
+    MenuDown,
+    MenuPageUp,
+    MenuPageDown,
+    MenuSelect,
+    MenuCancel,
+    SwitchTradeMode,
+    NextDestination,
+}
+
+/// When an action can be asked for. Keys may do different things in
+/// different contexts, but only one thing within each.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Context {
+    Game,
+    Menu,
+}
+
+impl Action {
+    pub const ALL: [Action; 28] = [
+        Action::MoveN,
+        Action::MoveS,
+        Action::MoveE,
+        Action::MoveW,
+        Action::MoveNE,
+        Action::MoveNW,
+        Action::MoveSE,
+        Action::MoveSW,
+        Action::PickUp,
+        Action::Inventory,
+        Action::Drop,
+        Action::ShowLog,
+        Action::CharacterSheet,
+        Action::LookAround,
+        Action::Wait,
+        Action::Rest,
+        Action::Explore,
+        Action::Travel,
+        Action::ShowBindings,
+        Action::SaveAndQuit,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuPageUp,
+        Action::MenuPageDown,
+        Action::MenuSelect,
+        Action::MenuCancel,
+        Action::SwitchTradeMode,
+        Action::NextDestination,
+    ];
+
+    pub fn context(self) -> Context {
+        match self {
+            Action::MenuUp
+            | Action::MenuDown
+            | Action::MenuPageUp
+            | Action::MenuPageDown
+            | Action::MenuSelect
+            | Action::MenuCancel
+            | Action::SwitchTradeMode
+            | Action::NextDestination => Context::Menu,
+            _ => Context::Game,
+        }
+    }
+
+    /// Which way a movement action goes.
+    pub fn direction(self) -> Option<(i32, i32)> {
+        match self {
+            Action::MoveN => Some((0, -1)),
+            Action::MoveS => Some((0, 1)),
+            Action::MoveE => Some((1, 0)),
+            Action::MoveW => Some((-1, 0)),
+            Action::MoveNE => Some((1, -1)),
+            Action::MoveNW => Some((-1, -1)),
+            Action::MoveSE => Some((1, 1)),
+            Action::MoveSW => Some((-1, 1)),
+            _ => None,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Action::MoveN => "Move north",
+            Action::MoveS => "Move south",
+            Action::MoveE => "Move east",
+            Action::MoveW => "Move west",
+            Action::MoveNE => "Move north-east",
+            Action::MoveNW => "Move north-west",
+            Action::MoveSE => "Move south-east",
+            Action::MoveSW => "Move south-west",
+            Action::PickUp => "Pick up",
+            Action::Inventory => "Inventory",
+            Action::Drop => "Drop an item",
+            Action::ShowLog => "Message log",
+            Action::CharacterSheet => "Character sheet",
+            Action::LookAround => "Look around",
+            Action::Wait => "Wait a turn",
+            Action::Rest => "Rest until healed",
+            Action::Explore => "Explore",
+            Action::Travel => "Travel to a place",
+            Action::ShowBindings => "Key bindings",
+            Action::SaveAndQuit => "Save and quit",
+            Action::MenuUp => "Menu: up",
+            Action::MenuDown => "Menu: down",
+            Action::MenuPageUp => "Menu: previous page",
+            Action::MenuPageDown => "Menu: next page",
+            Action::MenuSelect => "Menu: select",
+            Action::MenuCancel => "Menu: close",
+            Action::SwitchTradeMode => "Trade: buy or sell",
+            Action::NextDestination => "Travel: next item",
+        }
+    }
+}
+
+// every key a keymap file can name, by its VirtualKeyCode name
+const NAMEABLE_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Numpad0,
+    VirtualKeyCode::Numpad1,
+    VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3,
+    VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6,
+    VirtualKeyCode::Numpad7,
+    VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Home,
+    VirtualKeyCode::End,
+    VirtualKeyCode::PageUp,
+    VirtualKeyCode::PageDown,
+    VirtualKeyCode::Insert,
+    VirtualKeyCode::Delete,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Back,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::Slash,
+    VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Minus,
+    VirtualKeyCode::Equals,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+];
+
+pub fn key_name(key: VirtualKeyCode) -> String {
+    format!("{:?}", key)
+}
+
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    NAMEABLE_KEYS.iter().find(|key| key_name(**key) == name).copied()
+}
+
+/// Which keys trigger which actions.
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<VirtualKeyCode>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        use VirtualKeyCode::*;
+
+        let defaults: [(Action, Vec<VirtualKeyCode>); 28] = [
+            (Action::MoveN, vec![Up, Numpad8, K]),
+            (Action::MoveS, vec![Down, Numpad2, J]),
+            (Action::MoveE, vec![Right, Numpad6, L]),
+            (Action::MoveW, vec![Left, Numpad4, H]),
+            (Action::MoveNE, vec![Numpad9, U]),
+            (Action::MoveNW, vec![Numpad7, Y]),
+            (Action::MoveSE, vec![Numpad3, N]),
+            (Action::MoveSW, vec![Numpad1, B]),
+            (Action::PickUp, vec![G]),
+            (Action::Inventory, vec![I]),
+            (Action::Drop, vec![D]),
+            (Action::ShowLog, vec![M]),
+            (Action::CharacterSheet, vec![C]),
+            (Action::LookAround, vec![X]),
+            (Action::Wait, vec![Period, Numpad5]),
+            (Action::Rest, vec![R]),
+            (Action::Explore, vec![O]),
+            (Action::Travel, vec![T]),
+            (Action::ShowBindings, vec![Slash, F1]),
+            (Action::SaveAndQuit, vec![Escape]),
+            (Action::MenuUp, vec![Up]),
+            (Action::MenuDown, vec![Down]),
+            (Action::MenuPageUp, vec![PageUp, Left]),
+            (Action::MenuPageDown, vec![PageDown, Right]),
+            (Action::MenuSelect, vec![Return]),
+            (Action::MenuCancel, vec![Escape]),
+            (Action::SwitchTradeMode, vec![Tab]),
+            (Action::NextDestination, vec![Space]),
+        ];
+
+        Keymap {
+            bindings: defaults.iter().cloned().collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Reads the keymap file if there is one. Any problems with it are
+    /// returned for the player to see, and if it can't be used at all the
+    /// default bindings are used instead.
+    pub fn load() -> (Keymap, Vec<String>) {
+        if !Path::new(KEYMAP_PATH).exists() {
+            return (Keymap::default(), Vec::new());
+        }
+
+        match fs::read_to_string(KEYMAP_PATH) {
+            Ok(text) => Keymap::from_json(&text),
+            Err(e) => (
+                Keymap::default(),
+                vec![format!("Unable to read {}: {}", KEYMAP_PATH, e)],
+            ),
+        }
+    }
+
+    /// Builds a keymap from a JSON object mapping action names to lists of
+    /// key names. Actions it leaves out keep their default keys.
+    pub fn from_json(text: &str) -> (Keymap, Vec<String>) {
+        let named: HashMap<Action, Vec<String>> = match serde_json::from_str(text) {
+            Ok(named) => named,
+            Err(e) => {
+                return (
+                    Keymap::default(),
+                    vec![format!("Unable to understand {}: {}", KEYMAP_PATH, e)],
+                )
+            }
+        };
+
+        let mut keymap = Keymap::default();
+        let mut problems: Vec<String> = Vec::new();
+        for (action, names) in named {
+            let mut keys: Vec<VirtualKeyCode> = Vec::new();
+            for name in names.iter() {
+                match parse_key(name) {
+                    Some(key) => keys.push(key),
+                    None => problems.push(format!("Unknown key {} for {:?}", name, action)),
+                }
+            }
+            keymap.bindings.insert(action, keys);
+        }
+
+        let mut conflicts = keymap.conflicts();
+        if !conflicts.is_empty() {
+            problems.append(&mut conflicts);
+            problems.push("Using the default key bindings instead.".to_string());
+            return (Keymap::default(), problems);
+        }
+
+        (keymap, problems)
+    }
+
+    /// Keys bound to more than one action in the same context.
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut bound: HashMap<(VirtualKeyCode, Context), Action> = HashMap::new();
+        let mut conflicts: Vec<String> = Vec::new();
+
+        for action in Action::ALL.iter() {
+            for key in self.keys_for(*action).iter() {
+                match bound.get(&(*key, action.context())) {
+                    Some(other) if other != action => conflicts.push(format!(
+                        "{} is bound to both {:?} and {:?}",
+                        key_name(*key),
+                        other,
+                        action
+                    )),
+                    _ => {
+                        bound.insert((*key, action.context()), *action);
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    pub fn keys_for(&self, action: Action) -> &[VirtualKeyCode] {
+        self.bindings.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// The first key bound to an action, named the way prompts show it.
+    pub fn key_label(&self, action: Action) -> String {
+        self.keys_for(action).first().map_or_else(|| "UNBOUND".to_string(), |key| key_name(*key).to_uppercase())
+    }
+
+    /// The action a key press asks for, if any.
+    pub fn action(&self, key: Option<VirtualKeyCode>, context: Context) -> Option<Action> {
+        let key = key?;
+        Action::ALL
+            .iter()
+            .find(|action| action.context() == context && self.keys_for(**action).contains(&key))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert!(Keymap::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn file_overrides_only_the_actions_it_names() {
+        let (keymap, problems) = Keymap::from_json(r#"{ "PickUp": ["Comma"] }"#);
+
+        assert!(problems.is_empty());
+        assert_eq!(keymap.keys_for(Action::PickUp), &[VirtualKeyCode::Comma]);
+        assert_eq!(keymap.keys_for(Action::Inventory), &[VirtualKeyCode::I]);
+    }
+
+    #[test]
+    fn conflicting_file_falls_back_to_defaults() {
+        let (keymap, problems) = Keymap::from_json(r#"{ "PickUp": ["I"] }"#);
+
+        assert!(problems.iter().any(|p| p.contains("PickUp") && p.contains("Inventory")));
+        assert_eq!(keymap.keys_for(Action::PickUp), &[VirtualKeyCode::G]);
+    }
+
+    #[test]
+    fn same_key_may_mean_different_things_in_menus() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            keymap.action(Some(VirtualKeyCode::Escape), Context::Game),
+            Some(Action::SaveAndQuit)
+        );
+        assert_eq!(
+            keymap.action(Some(VirtualKeyCode::Escape), Context::Menu),
+            Some(Action::MenuCancel)
+        );
+    }
+
+    #[test]
+    fn prompts_name_the_rebound_key() {
+        let (keymap, _) = Keymap::from_json(r#"{ "MenuCancel": ["Q"] }"#);
+
+        assert_eq!(keymap.key_label(Action::MenuCancel), "Q");
+        assert_eq!(keymap.key_label(Action::MenuSelect), "RETURN");
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let (_, problems) = Keymap::from_json(r#"{ "Drop": ["NotAKey"] }"#);
+
+        assert_eq!(problems.len(), 1);
+    }
+}
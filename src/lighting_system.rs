@@ -0,0 +1,75 @@
+use specs::prelude::*;
+use rltk::{field_of_view_set, Point, RGB};
+use super::*;
+use std::collections::HashSet;
+
+/// The ship's own lighting, everywhere on a level that still has power.
+const POWERED_AMBIENT: RGB = RGB { r: 0.6, g: 0.6, b: 0.65 };
+
+/// Even the dimmest lit tile keeps this much of its colour.
+const MIN_SHADE: f32 = 0.4;
+/// How far a tile's colour is tinted towards the colour of its light.
+const LIGHT_TINT: f32 = 0.25;
+
+/// Works out how much light, and of what colour, falls on every tile.
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, LightSource>,
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, light_sources) = data;
+
+        let ambient = if map.is_powered() { POWERED_AMBIENT } else { RGB::named(rltk::BLACK) };
+        map.clear_light(ambient);
+
+        for (pos, source) in (&positions, &light_sources).join() {
+            let origin = Point::new(pos.x, pos.y);
+            for tile in lit_by(&map, origin, source.range).iter() {
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(origin, *tile);
+                let strength = 1.0 - distance / (source.range as f32 + 1.0);
+                if strength > 0.0 {
+                    map.add_light(tile.x, tile.y, source.colour * strength);
+                }
+            }
+        }
+    }
+}
+
+/// How a colour looks under the given light: dimmer the less light there is,
+/// and tinted towards the light's own colour.
+pub fn shade(colour: RGB, light: RGB) -> RGB {
+    let brightness = f32::max(light.r, f32::max(light.g, light.b));
+    if brightness <= 0.0 {
+        return RGB::named(rltk::BLACK);
+    }
+    let dimmed = colour * (MIN_SHADE + (1.0 - MIN_SHADE) * brightness);
+    let hue = light * (1.0 / brightness);
+    dimmed.lerp(hue * brightness, LIGHT_TINT)
+}
+
+// A lamp fixed to a wall can't see past the wall it is in, so it shines out
+// from the open tiles around it instead.
+fn lit_by(map: &Map, origin: Point, range: i32) -> HashSet<Point> {
+    if map.get_tile(origin.x, origin.y) != TileType::Wall {
+        return field_of_view_set(origin, range, map);
+    }
+
+    let (width, height) = map.get_dimensions();
+    let mut lit: HashSet<Point> = HashSet::new();
+    lit.insert(origin);
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let x = origin.x + dx;
+            let y = origin.y + dy;
+            if x < 0 || x >= width || y < 0 || y >= height || map.get_tile(x, y) == TileType::Wall {
+                continue;
+            }
+            lit.extend(field_of_view_set(Point::new(x, y), range, map));
+        }
+    }
+    lit
+}
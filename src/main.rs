@@ -8,7 +8,7 @@ mod components;
 use components::*;
 
 mod player;
-use player::{player_input, Player};
+use player::{player_input, Player, PlayerActivity};
 
 mod rect;
 use rect::*;
@@ -19,6 +19,20 @@ use map::*;
 mod game_log;
 use game_log::*;
 
+mod run_stats;
+use run_stats::RunStats;
+
+mod identification;
+use identification::Identification;
+
+mod trade;
+
+mod keymap;
+use keymap::Keymap;
+
+mod particle_system;
+use particle_system::{ParticleBuilder, ParticleSpawnSystem};
+
 mod damage_system;
 use damage_system::{delete_the_dead, DamageSystem};
 
@@ -31,6 +45,34 @@ use melee_combat_system::*;
 mod monster_ai_system;
 use monster_ai_system::*;
 
+mod monster_item_system;
+use monster_item_system::MonsterItemAI;
+
+mod initiative_system;
+use initiative_system::InitiativeSystem;
+
+mod regen_system;
+use regen_system::RegenSystem;
+
+mod detection_system;
+use detection_system::DetectionSystem;
+
+mod confusion_system;
+use confusion_system::ConfusionSystem;
+
+mod awareness_system;
+use awareness_system::{AwarenessSystem, Noises};
+
+mod distance_map;
+
+mod dice;
+
+mod pathing_system;
+use pathing_system::{PathingMaps, PathingSystem};
+
+mod lighting_system;
+use lighting_system::LightingSystem;
+
 mod vibility_system;
 use vibility_system::*;
 
@@ -45,16 +87,33 @@ mod saveload_system;
 mod gui;
 use gui::draw_ui;
 
+mod camera;
+use camera::Camera;
+
 mod spawner;
 
+mod factions;
+use factions::FactionTable;
+
+// how quickly a ship schematic sweeps down the map
+const MAP_REVEAL_ROWS_PER_FRAME: i32 = 2;
+
+// how many initiative ticks a frame may hand out before it gets drawn, so a
+// player too slow to ever act can't hang the game
+const MAX_TICKS_PER_FRAME: i32 = 100;
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum RunState {
     AwaitingInput,
     PreRun,
     PlayerTurn,
     MonsterTurn,
-    ShowInventory,
-    ShowDropItem,
+    ShowInventory {
+        selection: usize,
+    },
+    ShowDropItem {
+        selection: usize,
+    },
     ShowTargeting {
         range: i32,
         item: Entity,
@@ -62,7 +121,29 @@ pub enum RunState {
     MainMenu {
         menu_selection: gui::MainMenuSelection,
     },
+    CharacterCreation {
+        selection: usize,
+    },
     SaveGame,
+    ShowLog {
+        offset: usize,
+    },
+    CharacterSheet,
+    LookAt {
+        cursor: Point,
+    },
+    ShowBindings,
+    ChooseDestination {
+        cursor: Point,
+    },
+    Trade {
+        vendor: Entity,
+        selling: bool,
+        selection: usize,
+    },
+    MagicMapReveal {
+        row: i32,
+    },
 }
 
 pub struct State {
@@ -71,8 +152,52 @@ pub struct State {
 
 impl State {
     fn run_systems(&mut self) {
+        let mut initiative = InitiativeSystem {};
+        initiative.run_now(&self.ecs);
+
+        let mut regen = RegenSystem {};
+        regen.run_now(&self.ecs);
+
+        let mut detection = DetectionSystem {};
+        detection.run_now(&self.ecs);
+
+        let mut confusion = ConfusionSystem {};
+        confusion.run_now(&self.ecs);
+
+        self.run_view_systems();
+
+        let mut awareness = AwarenessSystem {};
+        awareness.run_now(&self.ecs);
+
+        let mut pathing = PathingSystem {};
+        pathing.run_now(&self.ecs);
+
+        self.run_action_systems();
+        self.ecs.maintain();
+    }
+
+    /// One tick of initiative, and whatever the monsters whose turn it is do
+    /// with it. Lighting and everyone's view are left until the player's turn
+    /// comes round.
+    fn run_monster_tick(&mut self) {
+        let mut initiative = InitiativeSystem {};
+        initiative.run_now(&self.ecs);
+
+        self.run_action_systems();
+        self.ecs.maintain();
+    }
+
+    fn run_view_systems(&mut self) {
+        let mut lighting = LightingSystem {};
+        lighting.run_now(&self.ecs);
+
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
+    }
+
+    fn run_action_systems(&mut self) {
+        let mut mob_items = MonsterItemAI {};
+        mob_items.run_now(&self.ecs);
 
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
@@ -95,7 +220,8 @@ impl State {
         let mut drop_items = ItemDropSystem {};
         drop_items.run_now(&self.ecs);
 
-        self.ecs.maintain();
+        let mut particles = ParticleSpawnSystem {};
+        particles.run_now(&self.ecs);
     }
 }
 
@@ -108,38 +234,40 @@ impl GameState for State {
         }
 
         ctx.cls();
+        particle_system::cull_dead_particles(&mut self.ecs, ctx);
 
         match new_run_state {
-            RunState::MainMenu { .. } => {
-                let result = gui::main_menu(self, ctx);
-                match result {
-                    gui::MainMenuResult::NoSelection{ selected } => new_run_state = RunState::MainMenu{ menu_selection: selected },
-                    gui::MainMenuResult::Selected{ selected } => {
-                        match selected {
-                            gui::MainMenuSelection::NewGame => new_run_state = RunState::PreRun,
-                            gui::MainMenuSelection::LoadGame => new_run_state = RunState::PreRun,
-                            gui::MainMenuSelection::Quit => { ::std::process::exit(0); }
-                        }
-                    }
-                }
-            }
+            RunState::MainMenu { .. } | RunState::CharacterCreation { .. } => {}
             _ => {
                 draw_map(&self.ecs, ctx);
 
                 {
                     // draw objects
+                    let entities = self.ecs.entities();
                     let positions = self.ecs.read_storage::<Position>();
                     let renderables = self.ecs.read_storage::<Renderable>();
+                    let monsters = self.ecs.read_storage::<Monster>();
                     let map = self.ecs.fetch::<Map>();
-
-                    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-                    for (pos, render) in data.iter() {
-                        if map.tile_is_visible(pos.x, pos.y) {
+                    let camera = Camera::new(&self.ecs);
+
+                    // a motion tracker shows where every creature is, seen or not
+                    let player_entity = self.ecs.fetch::<Entity>();
+                    let detecting = self.ecs.read_storage::<Detection>().contains(*player_entity);
+
+                    let mut data = (&entities, &positions, &renderables).join().collect::<Vec<_>>();
+                    data.sort_by_key(|&a| std::cmp::Reverse(a.2.render_order));
+                    for (entity, pos, render) in data.iter() {
+                        let visible = map.tile_is_visible(pos.x, pos.y);
+                        let detected = detecting && monsters.contains(*entity);
+                        if !visible && !detected {
+                            continue;
+                        }
+                        let foreground = if visible { render.foreground } else { RGB::named(rltk::MAGENTA) };
+                        if let Some(screen) = camera.world_to_screen(Point::new(pos.x, pos.y)) {
                             ctx.set(
-                                pos.x,
-                                pos.y,
-                                render.foreground,
+                                screen.x,
+                                screen.y,
+                                foreground,
                                 render.background,
                                 render.glyph,
                             )
@@ -161,27 +289,64 @@ impl GameState for State {
                 new_run_state = player_input(self, ctx);
             }
             RunState::PlayerTurn => {
+                {
+                    let mut stats = self.ecs.fetch_mut::<RunStats>();
+                    stats.turns += 1;
+                    self.ecs.fetch_mut::<GameLog>().set_turn(stats.turns);
+                }
                 self.run_systems();
                 self.ecs.maintain();
-                new_run_state = RunState::MonsterTurn;
+                // a ship schematic gets to show itself off before anyone moves
+                new_run_state = match *self.ecs.fetch::<RunState>() {
+                    RunState::MagicMapReveal { .. } => RunState::MagicMapReveal { row: 0 },
+                    _ => RunState::MonsterTurn,
+                };
+            }
+            RunState::MagicMapReveal { row } => {
+                let mut map = self.ecs.fetch_mut::<Map>();
+                let (_, height) = map.get_dimensions();
+                for y in row..i32::min(height, row + MAP_REVEAL_ROWS_PER_FRAME) {
+                    map.reveal_row(y);
+                }
+                new_run_state = if row + MAP_REVEAL_ROWS_PER_FRAME >= height {
+                    RunState::MonsterTurn
+                } else {
+                    RunState::MagicMapReveal { row: row + MAP_REVEAL_ROWS_PER_FRAME }
+                };
             }
             RunState::MonsterTurn => {
-                self.run_systems();
-                self.ecs.maintain();
-                new_run_state = RunState::AwaitingInput;
+                // keep handing out turns until it is the player's go, or until
+                // it's time to draw another frame
+                let mut ticks = 0;
+                while new_run_state == RunState::MonsterTurn && ticks < MAX_TICKS_PER_FRAME {
+                    self.run_monster_tick();
+                    delete_the_dead(&mut self.ecs);
+                    new_run_state = *self.ecs.fetch::<RunState>();
+                    ticks += 1;
+                }
+                // bring the view up to date with wherever the monsters went
+                self.run_view_systems();
             }
-            RunState::ShowInventory => {
-                let result = gui::show_inventory(self, ctx);
+            RunState::ShowInventory { selection } => {
+                let result = gui::show_inventory(self, ctx, selection);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Highlight { selection } => {
+                        new_run_state = RunState::ShowInventory { selection }
+                    }
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
                         let is_ranged = self.ecs.read_storage::<Ranged>();
                         let is_item_ranged = is_ranged.get(item_entity);
                         if let Some(is_item_ranged) = is_item_ranged {
+                            // practised hands reach further
+                            let player_entity = self.ecs.fetch::<Entity>();
+                            let skill = self.ecs.read_storage::<Skills>()
+                                .get(*player_entity)
+                                .map_or(0, |s| s.level(Skill::Ranged));
                             new_run_state = RunState::ShowTargeting {
-                                range: is_item_ranged.range,
+                                range: is_item_ranged.range + skill,
                                 item: item_entity,
                             };
                         } else {
@@ -200,11 +365,14 @@ impl GameState for State {
                     }
                 }
             }
-            RunState::ShowDropItem => {
-                let result = gui::drop_item_menu(self, ctx);
+            RunState::ShowDropItem { selection } => {
+                let result = gui::drop_item_menu(self, ctx, selection);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Highlight { selection } => {
+                        new_run_state = RunState::ShowDropItem { selection }
+                    }
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
                         let mut intent = self.ecs.write_storage::<WantsToDropItem>();
@@ -222,7 +390,7 @@ impl GameState for State {
                 let result = gui::ranged_target(self, ctx, range);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
-                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::NoResponse | gui::ItemMenuResult::Highlight { .. } => {}
                     gui::ItemMenuResult::Selected => {
                         let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                         intent
@@ -244,14 +412,92 @@ impl GameState for State {
                     gui::MainMenuResult::NoSelection{ selected } => new_run_state = RunState::MainMenu{ menu_selection: selected },
                     gui::MainMenuResult::Selected{ selected } => {
                         match selected {
-                            gui::MainMenuSelection::NewGame => new_run_state = RunState::PreRun,
-                            gui::MainMenuSelection::LoadGame => new_run_state = RunState::PreRun,
+                            gui::MainMenuSelection::NewGame => {
+                                // a character is only made once, before their first turn
+                                if self.ecs.fetch::<RunStats>().turns == 0 {
+                                    new_run_state = RunState::CharacterCreation { selection: 0 };
+                                } else {
+                                    new_run_state = RunState::PreRun;
+                                }
+                            }
+                            gui::MainMenuSelection::LoadGame => {
+                                saveload_system::load_game(&mut self.ecs);
+                                saveload_system::delete_save();
+                                new_run_state = RunState::PreRun;
+                            }
                             gui::MainMenuSelection::Quit => { ::std::process::exit(0); }
                         }
                     }
                 }
             }
+            RunState::CharacterCreation { selection } => {
+                match gui::character_creation(self, ctx, selection) {
+                    gui::CreationResult::NoResponse { selection } => {
+                        new_run_state = RunState::CharacterCreation { selection }
+                    }
+                    gui::CreationResult::Done => {
+                        let player_entity = *self.ecs.fetch::<Entity>();
+                        spawner::apply_attributes(&mut self.ecs, player_entity);
+                        new_run_state = RunState::PreRun;
+                    }
+                }
+            }
+            RunState::ShowLog { offset } => {
+                let result = gui::show_log(self, ctx, offset);
+                match result {
+                    gui::LogScreenResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::LogScreenResult::NoResponse { offset } => new_run_state = RunState::ShowLog { offset },
+                }
+            }
+            RunState::LookAt { cursor } => {
+                match gui::look_at(self, ctx, cursor) {
+                    gui::LookResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::LookResult::NoResponse { cursor } => {
+                        new_run_state = RunState::LookAt { cursor }
+                    }
+                }
+            }
+            RunState::ChooseDestination { cursor } => {
+                match gui::choose_destination(self, ctx, cursor) {
+                    gui::DestinationResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::DestinationResult::NoResponse { cursor } => {
+                        new_run_state = RunState::ChooseDestination { cursor }
+                    }
+                    gui::DestinationResult::Selected { destination } => {
+                        new_run_state = player::travel_to(&mut self.ecs, destination);
+                    }
+                }
+            }
+            RunState::Trade {
+                vendor,
+                selling,
+                selection,
+            } => match gui::show_trade(self, ctx, vendor, selling, selection) {
+                gui::TradeResult::Cancel => new_run_state = RunState::AwaitingInput,
+                gui::TradeResult::NoResponse { selling, selection } => {
+                    new_run_state = RunState::Trade {
+                        vendor,
+                        selling,
+                        selection,
+                    }
+                }
+                gui::TradeResult::Buy(item) => trade::buy(&mut self.ecs, item),
+                gui::TradeResult::Sell(item) => trade::sell(&mut self.ecs, vendor, item),
+            },
+            RunState::ShowBindings => {
+                let result = gui::show_bindings(self, ctx);
+                if result == gui::ItemMenuResult::Cancel {
+                    new_run_state = RunState::AwaitingInput;
+                }
+            }
+            RunState::CharacterSheet => {
+                let result = gui::show_character_sheet(self, ctx);
+                if result == gui::ItemMenuResult::Cancel {
+                    new_run_state = RunState::AwaitingInput;
+                }
+            }
             RunState::SaveGame => {
+                saveload_system::save_game(&mut self.ecs);
                 new_run_state = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::LoadGame }
             }
         }
@@ -266,8 +512,8 @@ impl GameState for State {
 }
 
 fn main() {
-    const MAP_WIDTH: i32 = 80;
-    const MAP_HEIGHT: i32 = 43;
+    const MAP_WIDTH: i32 = camera::VIEW_WIDTH * 2;
+    const MAP_HEIGHT: i32 = camera::VIEW_HEIGHT * 2;
 
     use rltk::RltkBuilder;
     let context = RltkBuilder::simple80x50().with_title("Star Rogue").build();
@@ -278,7 +524,8 @@ fn main() {
 }
 
 fn build_state(width: i32, height: i32) -> State {
-    let map = Map::new(width, height);
+    let mut map = Map::new(width, height);
+    map.set_corner_cutting(false);
     let mut gs = State { ecs: World::new() };
     // register components
     gs.ecs.register::<Position>();
@@ -286,12 +533,26 @@ fn build_state(width: i32, height: i32) -> State {
     gs.ecs.register::<LeftMover>();
     gs.ecs.register::<Player>();
     gs.ecs.register::<ViewShed>();
+    gs.ecs.register::<LightSource>();
     gs.ecs.register::<Monster>();
+    gs.ecs.register::<Wandering>();
+    gs.ecs.register::<Hunting>();
+    gs.ecs.register::<Fleeing>();
+    gs.ecs.register::<Faction>();
+    gs.ecs.register::<Initiative>();
+    gs.ecs.register::<MyTurn>();
     gs.ecs.register::<Name>();
     gs.ecs.register::<BlocksTile>();
     gs.ecs.register::<CombatStats>();
+    gs.ecs.register::<Attributes>();
+    gs.ecs.register::<Skills>();
+    gs.ecs.register::<TechItem>();
+    gs.ecs.register::<Asleep>();
+    gs.ecs.register::<Unaware>();
+    gs.ecs.register::<Experience>();
     gs.ecs.register::<WantsToMelee>();
     gs.ecs.register::<SufferDamage>();
+    gs.ecs.register::<Description>();
     gs.ecs.register::<Item>();
     gs.ecs.register::<ProvidesHealing>();
     gs.ecs.register::<InBackPack>();
@@ -299,14 +560,40 @@ fn build_state(width: i32, height: i32) -> State {
     gs.ecs.register::<WantsToUseItem>();
     gs.ecs.register::<WantsToDropItem>();
     gs.ecs.register::<Consumable>();
+    gs.ecs.register::<Value>();
+    gs.ecs.register::<Currency>();
+    gs.ecs.register::<Vendor>();
+    gs.ecs.register::<Identifies>();
+    gs.ecs.register::<UsesItems>();
+    gs.ecs.register::<LootTable>();
+    gs.ecs.register::<LeavesWreckage>();
+    gs.ecs.register::<ThrowsItems>();
     gs.ecs.register::<Ranged>();
     gs.ecs.register::<InflictsDamage>();
     gs.ecs.register::<AreaOfEffect>();
     gs.ecs.register::<Confusion>();
+    gs.ecs.register::<Invigorated>();
+    gs.ecs.register::<GrantsInvigoration>();
+    gs.ecs.register::<Regenerates>();
+    gs.ecs.register::<RevealsMap>();
+    gs.ecs.register::<Detection>();
+    gs.ecs.register::<GrantsDetection>();
+    gs.ecs.register::<MeleeWeapon>();
+    gs.ecs.register::<NaturalAttack>();
+    gs.ecs.register::<Equipped>();
     gs.ecs.register::<SimpleMarker<SerializeMe>>();
+    gs.ecs.register::<SerializationHelper>();
+    gs.ecs.register::<ParticleLifetime>();
 
     gs.ecs.insert(rltk::RandomNumberGenerator::new());
+    let identification = Identification::new(&mut gs.ecs.write_resource::<rltk::RandomNumberGenerator>());
+    gs.ecs.insert(identification);
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+    gs.ecs.insert(FactionTable::new());
+    gs.ecs.insert(PathingMaps::new(&map));
+    gs.ecs.insert(ParticleBuilder::default());
+    gs.ecs.insert(Noises::default());
+    gs.ecs.insert(PlayerActivity::default());
 
     let (player_x, player_y) = map.get_room(0).centre();
 
@@ -314,53 +601,85 @@ fn build_state(width: i32, height: i32) -> State {
     let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
     gs.ecs.insert(player_entity);
 
+    // the second room is a quiet one, with a trader in it
+    if map.get_room_count() > 1 {
+        let (trader_x, trader_y) = map.get_room(1).centre();
+        spawner::trader(&mut gs.ecs, trader_x, trader_y);
+    }
+
     // create some enemies
-    for i in 1..map.get_room_count() {
-        // skip the first room
+    for i in 2..map.get_room_count() {
+        // skip the first two rooms
         let room = map.get_room(i);
         spawner::spawn_room(&mut gs.ecs, width, &room);
     }
+    spawner::wall_lamps(&mut gs.ecs, &map);
+    let powered = map.is_powered();
 
     gs.ecs.insert(map);
     gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(RunState::PreRun);
-    gs.ecs.insert(GameLog {
-        entries: vec!["Welcome to Star Rogue!".to_string()],
+    gs.ecs.insert(RunState::MainMenu {
+        menu_selection: gui::MainMenuSelection::NewGame,
     });
+    let mut log = GameLog::default();
+    log.entry()
+        .text("Welcome to ")
+        .coloured("Star Rogue", RGB::named(rltk::YELLOW))
+        .text("!")
+        .log();
+    if !powered {
+        log.entry()
+            .text("The ship's power is out. It's ")
+            .coloured("dark", RGB::named(rltk::GREY))
+            .text(" in here.")
+            .log();
+    }
+
+    let (keymap, problems) = Keymap::load();
+    for problem in problems.iter() {
+        log.entry().coloured(problem, RGB::named(rltk::RED)).log();
+    }
+    gs.ecs.insert(keymap);
+    gs.ecs.insert(log);
+    gs.ecs.insert(RunStats::default());
 
     gs
 }
 
 fn draw_map(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
+    let camera = Camera::new(ecs);
 
     let wall: u8 = rltk::to_cp437('#');
     let path: u8 = rltk::to_cp437('.');
     let black = RGB::named(rltk::BLACK);
 
-    let (width, height) = map.get_dimensions();
-
-    for x in 0..width {
-        for y in 0..height {
-            if map.tile_is_revealed(x, y) {
-                let tile = map.get_tile(x, y);
-                let glyph;
-                let mut fg;
-                match tile {
-                    TileType::Floor => {
-                        glyph = path;
-                        fg = RGB::from_f32(0.0, 0.5, 0.5);
-                    }
-                    TileType::Wall => {
-                        glyph = wall;
-                        fg = RGB::from_f32(0., 1.0, 0.);
-                    }
-                };
+    for screen_x in 0..camera::VIEW_WIDTH {
+        for screen_y in 0..camera::VIEW_HEIGHT {
+            let world = camera.screen_to_world(Point::new(screen_x, screen_y));
+            if let Some(Point { x, y }) = world {
+                if map.tile_is_revealed(x, y) {
+                    let tile = map.get_tile(x, y);
+                    let glyph;
+                    let mut fg;
+                    match tile {
+                        TileType::Floor => {
+                            glyph = path;
+                            fg = RGB::from_f32(0.0, 0.5, 0.5);
+                        }
+                        TileType::Wall => {
+                            glyph = wall;
+                            fg = RGB::from_f32(0., 1.0, 0.);
+                        }
+                    };
 
-                if !map.tile_is_visible(x, y) {
-                    fg = fg.to_greyscale()
+                    if map.tile_is_visible(x, y) {
+                        fg = lighting_system::shade(fg, map.get_light(x, y));
+                    } else {
+                        fg = fg.to_greyscale()
+                    }
+                    ctx.set(screen_x, screen_y, fg, black, glyph);
                 }
-                ctx.set(x, y, fg, black, glyph);
             }
         }
     }
@@ -1,5 +1,5 @@
 use super::Rect;
-use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator};
+use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator, RGB};
 use std::cmp::{max, min};
 use specs::{Entity};
 use serde::{Serialize, Deserialize};
@@ -10,6 +10,10 @@ pub enum TileType {
     Floor,
 }
 
+/// The least light, in its brightest channel, a tile needs for anything on it
+/// to be made out.
+pub const MIN_VISIBLE_LIGHT: f32 = 0.1;
+
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Map {
     width: i32,
@@ -19,6 +23,9 @@ pub struct Map {
     revealed_tiles: Vec<bool>,
     visible_tiles: Vec<bool>,
     blocked_tiles: Vec<bool>,
+    light: Vec<RGB>,
+    powered: bool,
+    allow_corner_cutting: bool,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
@@ -35,17 +42,24 @@ impl Map {
             revealed_tiles: vec![false; (width * height) as usize],
             visible_tiles: vec![false; (width * height) as usize],
             blocked_tiles: vec![false; (width * height) as usize],
+            light: vec![RGB::new(); (width * height) as usize],
+            powered: true,
+            allow_corner_cutting: true,
             tile_content: vec![Vec::new(); (width * height) as usize],
         };
 
-        const MAX_ROOMS: i32 = 30;
+        // roughly thirty rooms on a single screen's worth of map
+        const TILES_PER_ROOM: i32 = 115;
         const MIN_SIZE: i32 = 4;
         const MAX_SIZE: i32 = 10;
 
         let mut rng = RandomNumberGenerator::new();
 
+        // some wrecks have lost their power, and with it their lights
+        map.powered = rng.roll_dice(1, 4) != 1;
+
         // add rooms
-        for _ in 0..MAX_ROOMS {
+        for _ in 0..(width * height) / TILES_PER_ROOM {
             let w = rng.range(MIN_SIZE, MAX_SIZE);
             let h = rng.range(MIN_SIZE, MAX_SIZE);
             let x = rng.roll_dice(1, width - w - 1) - 1;
@@ -93,6 +107,9 @@ impl Map {
     }
 
     pub fn clear_content_index(&mut self) {
+        // the index isn't saved, so it comes back empty after a load
+        self.tile_content
+            .resize((self.width * self.height) as usize, Vec::new());
         for content in self.tile_content.iter_mut() {
             content.clear();
         }
@@ -119,6 +136,11 @@ impl Map {
         self.blocked_tiles[idx] = false;
     }
 
+    /// Whether diagonal moves may squeeze past the corner of a wall.
+    pub fn set_corner_cutting(&mut self, allowed: bool) {
+        self.allow_corner_cutting = allowed;
+    }
+
     pub fn get_room(&self, index: i32) -> Rect {
         self.rooms[index as usize]
     }
@@ -144,6 +166,17 @@ impl Map {
         self.revealed_tiles[idx] = true;
     }
 
+    /// Reveals everything on a row that could ever be seen. Solid rock, with
+    /// no open tile next to it, stays hidden.
+    pub fn reveal_row(&mut self, y: i32) {
+        for x in 0..self.width {
+            let seeable = (-1..=1).any(|dx| (-1..=1).any(|dy| !self.is_wall(x + dx, y + dy)));
+            if seeable {
+                self.reveal_tile(x, y);
+            }
+        }
+    }
+
     pub fn clear_visible_tiles(&mut self) {
         for t in self.visible_tiles.iter_mut() {
             *t = false
@@ -155,6 +188,33 @@ impl Map {
         self.visible_tiles[idx] = true;
     }
 
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    /// Sets every tile back to the light that is everywhere, before any
+    /// light sources are added.
+    pub fn clear_light(&mut self, ambient: RGB) {
+        for light in self.light.iter_mut() {
+            *light = ambient;
+        }
+    }
+
+    pub fn add_light(&mut self, x: i32, y: i32, light: RGB) {
+        let idx = self.xy_idx(x, y);
+        let total = self.light[idx] + light;
+        self.light[idx] = RGB::from_f32(f32::min(1.0, total.r), f32::min(1.0, total.g), f32::min(1.0, total.b));
+    }
+
+    pub fn get_light(&self, x: i32, y: i32) -> RGB {
+        self.light[self.xy_idx(x, y)]
+    }
+
+    pub fn tile_is_lit(&self, x: i32, y: i32) -> bool {
+        let light = self.get_light(x, y);
+        f32::max(light.r, f32::max(light.g, light.b)) >= MIN_VISIBLE_LIGHT
+    }
+
     pub fn get_tile(&self, x: i32, y: i32) -> TileType {
         self.tiles[self.xy_idx(x, y)]
     }
@@ -188,13 +248,52 @@ impl Map {
         }
     }
 
-    fn is_exit_valid(&self, x: i32, y: i32) -> bool {
-        if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
+    /// The tiles one step away from `idx`, and what each step costs. With
+    /// `walls_only`, nothing standing about gets in the way.
+    pub fn exits(&self, idx: usize, walls_only: bool) -> Vec<(usize, f32)> {
+        let mut exits = Vec::<(usize, f32)>::new();
+        let x = idx as i32 % self.width;
+        let y = idx as i32 / self.width;
+
+        // cardinal directions
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+            if self.is_exit_valid(x + dx, y + dy, walls_only) {
+                exits.push((self.xy_idx(x + dx, y + dy), 1.0));
+            }
+        }
+
+        // diagonal directions
+        for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter() {
+            if !self.is_exit_valid(x + dx, y + dy, walls_only) {
+                continue;
+            }
+            if !self.allow_corner_cutting && (self.is_wall(x + dx, y) || self.is_wall(x, y + dy)) {
+                continue;
+            }
+            exits.push((self.xy_idx(x + dx, y + dy), 1.45));
+        }
+
+        exits
+    }
+
+    fn is_exit_valid(&self, x: i32, y: i32, walls_only: bool) -> bool {
+        if !self.in_bounds(x, y) {
             return false;
         }
+        if walls_only {
+            return !self.is_wall(x, y);
+        }
         let idx = self.xy_idx(x, y);
         !self.blocked_tiles[idx]
     }
+
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        !self.in_bounds(x, y) || self.get_tile(x, y) == TileType::Wall
+    }
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x < self.width && y >= 0 && y < self.height
+    }
 }
 
 impl Algorithm2D for Map {
@@ -205,50 +304,203 @@ impl Algorithm2D for Map {
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize] == TileType::Wall
+        self.tiles[idx] == TileType::Wall
     }
 
-    fn get_available_exits(&self, _idx: usize) -> Vec<(usize, f32)> {
-        let mut exits = Vec::<(usize, f32)>::new();
-        let x = _idx as i32 % self.width;
-        let y = _idx as i32 / self.width;
+    fn get_available_exits(&self, idx: usize) -> Vec<(usize, f32)> {
+        self.exits(idx, false)
+    }
+
+    fn get_pathing_distance(&self, _idx1: usize, _idx2: usize) -> f32 {
         let w = self.width as usize;
+        let p1 = Point::new(_idx1 % w, _idx1 / w);
+        let p2 = Point::new(_idx2 % w, _idx2 / w);
+        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+    }
+}
 
-        // cardinal directions
-        if self.is_exit_valid(x - 1, y) {
-            exits.push((_idx - 1, 1.0))
-        };
-        if self.is_exit_valid(x + 1, y) {
-            exits.push((_idx + 1, 1.0))
-        };
-        if self.is_exit_valid(x + 1, y) {
-            exits.push((_idx - w, 1.0))
-        };
-        if self.is_exit_valid(x + 1, y) {
-            exits.push((_idx + w, 1.0))
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rltk::a_star_search;
 
-        // diagonal directions
-        if self.is_exit_valid(x-1, y-1) {
-            exits.push(((_idx - w) - 1, 1.45));
-        };
-        if self.is_exit_valid(x+1, y-1) {
-            exits.push(((_idx - w) + 1, 1.45));
-        };
-        if self.is_exit_valid(x-1, y+1) {
-            exits.push(((_idx + w) - 1, 1.45));
-        };
-        if self.is_exit_valid(x+1, y+1) {
-            exits.push(((_idx + w) + 1, 1.45));
+    // '#' is a wall, anything else is floor
+    fn fixture(rows: &[&str]) -> Map {
+        let width = rows[0].len() as i32;
+        let height = rows.len() as i32;
+        let size = (width * height) as usize;
+        let mut map = Map {
+            width,
+            height,
+            tiles: Vec::with_capacity(size),
+            rooms: Vec::new(),
+            revealed_tiles: vec![false; size],
+            visible_tiles: vec![false; size],
+            blocked_tiles: vec![false; size],
+            light: vec![RGB::new(); size],
+            powered: true,
+            allow_corner_cutting: true,
+            tile_content: vec![Vec::new(); size],
         };
 
+        for row in rows.iter() {
+            for c in row.chars() {
+                map.tiles.push(if c == '#' {
+                    TileType::Wall
+                } else {
+                    TileType::Floor
+                });
+            }
+        }
+        map.update_blocked_tiles();
+
+        map
+    }
+
+    fn path(map: &Map, from: (i32, i32), to: (i32, i32)) -> rltk::NavigationPath {
+        a_star_search(map.xy_idx(from.0, from.1), map.xy_idx(to.0, to.1), map)
+    }
+
+    fn exits_of(map: &Map, x: i32, y: i32) -> Vec<usize> {
+        let mut exits: Vec<usize> = map
+            .get_available_exits(map.xy_idx(x, y))
+            .iter()
+            .map(|exit| exit.0)
+            .collect();
+        exits.sort();
         exits
     }
 
-    fn get_pathing_distance(&self, _idx1: usize, _idx2: usize) -> f32 {
-        let w = self.width as usize;
-        let p1 = Point::new(_idx1 % w, _idx1 / w);
-        let p2 = Point::new(_idx2 % w, _idx2 / w);
-        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+    #[test]
+    fn open_tile_has_all_eight_exits() {
+        let map = fixture(&["...", "...", "..."]);
+        assert_eq!(exits_of(&map, 1, 1), vec![0, 1, 2, 3, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn north_and_south_exits_ignore_east_wall() {
+        let map = fixture(&[".#", ".#", ".#"]);
+        assert_eq!(exits_of(&map, 0, 1), vec![0, 4]);
+    }
+
+    #[test]
+    fn edge_tiles_do_not_underflow() {
+        let map = fixture(&["...", "...", "..."]);
+        assert_eq!(exits_of(&map, 0, 0), vec![1, 3, 4]);
+        assert_eq!(exits_of(&map, 2, 2), vec![4, 5, 7]);
+    }
+
+    #[test]
+    fn straight_corridor_path_length() {
+        let map = fixture(&["#######", "#.....#", "#######"]);
+        let path = path(&map, (1, 1), (5, 1));
+        assert!(path.success);
+        // steps include the starting tile
+        assert_eq!(path.steps.len(), 5);
+    }
+
+    #[test]
+    fn vertical_corridor_is_walkable() {
+        let map = fixture(&["###", "#.#", "#.#", "#.#", "###"]);
+        let path = path(&map, (1, 1), (1, 3));
+        assert!(path.success);
+        assert_eq!(path.steps.len(), 3);
+    }
+
+    #[test]
+    fn path_goes_around_walls() {
+        let map = fixture(&[
+            "#######",
+            "#..#..#",
+            "#..#..#",
+            "#.....#",
+            "#######",
+        ]);
+        let path = path(&map, (1, 1), (5, 1));
+        assert!(path.success);
+        for step in path.steps.iter() {
+            assert!(map.tiles[*step] == TileType::Floor);
+        }
+        assert_eq!(*path.steps.last().unwrap(), map.xy_idx(5, 1));
+        assert_eq!(path.steps.len(), 5);
+    }
+
+    #[test]
+    fn walled_off_tile_is_unreachable() {
+        let map = fixture(&["#####", "#.#.#", "#####"]);
+        let path = path(&map, (1, 1), (3, 1));
+        assert!(!path.success);
+    }
+
+    #[test]
+    fn blocked_tiles_are_respected() {
+        let mut map = fixture(&["#####", "#...#", "#####"]);
+        map.set_tile_as_blocked(2, 1);
+        let path = path(&map, (1, 1), (3, 1));
+        assert!(!path.success);
+    }
+
+    #[test]
+    fn walls_only_exits_ignore_blocked_tiles() {
+        let mut map = fixture(&["#####", "#...#", "#####"]);
+        map.set_tile_as_blocked(2, 1);
+        let idx = map.xy_idx(1, 1);
+        assert!(!map.exits(idx, false).iter().any(|exit| exit.0 == map.xy_idx(2, 1)));
+        assert_eq!(map.exits(idx, true), vec![(map.xy_idx(2, 1), 1.0)]);
+    }
+
+    #[test]
+    fn diagonal_may_cut_corners_when_allowed() {
+        let map = fixture(&["####", "#.##", "##.#", "####"]);
+        let path = path(&map, (1, 1), (2, 2));
+        assert!(path.success);
+        assert_eq!(path.steps.len(), 2);
+    }
+
+    #[test]
+    fn diagonal_cannot_cut_corners_when_disallowed() {
+        let mut map = fixture(&["####", "#.##", "##.#", "####"]);
+        map.set_corner_cutting(false);
+        let path = path(&map, (1, 1), (2, 2));
+        assert!(!path.success);
+    }
+
+    #[test]
+    fn either_wall_corner_blocks_the_diagonal() {
+        let mut map = fixture(&["####", "#..#", "#.##", "####"]);
+        map.set_corner_cutting(false);
+        assert_eq!(exits_of(&map, 1, 2), vec![map.xy_idx(1, 1)]);
+
+        let mut open = fixture(&["####", "#..#", "#..#", "####"]);
+        open.set_corner_cutting(false);
+        assert!(exits_of(&open, 1, 2).contains(&open.xy_idx(2, 1)));
+    }
+
+    #[test]
+    fn revealing_a_row_leaves_solid_rock_hidden() {
+        let mut map = fixture(&["#####", "#####", "###.#", "#####"]);
+        map.reveal_row(1);
+        let revealed: Vec<bool> = (0..5).map(|x| map.tile_is_revealed(x, 1)).collect();
+        assert_eq!(revealed, vec![false, false, true, true, true]);
+        assert!(!map.tile_is_revealed(3, 2));
+    }
+
+    #[test]
+    fn light_adds_up_but_is_capped() {
+        let mut map = fixture(&["..."]);
+        map.clear_light(RGB::from_f32(0.5, 0.0, 0.0));
+        map.add_light(1, 0, RGB::from_f32(0.75, 0.25, 0.0));
+        assert_eq!(map.get_light(1, 0), RGB::from_f32(1.0, 0.25, 0.0));
+        assert_eq!(map.get_light(0, 0), RGB::from_f32(0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn dim_tiles_are_not_lit() {
+        let mut map = fixture(&[".."]);
+        map.clear_light(RGB::new());
+        map.add_light(0, 0, RGB::from_f32(0.0, 0.0, MIN_VISIBLE_LIGHT));
+        map.add_light(1, 0, RGB::from_f32(0.0, 0.0, MIN_VISIBLE_LIGHT / 2.0));
+        assert!(map.tile_is_lit(0, 0));
+        assert!(!map.tile_is_lit(1, 0));
     }
 }
@@ -1,38 +1,153 @@
 use specs::prelude::*;
+use rltk::{DiceType, Point, RandomNumberGenerator, RGB};
 use super::*;
+use particle_system::{ParticleBuilder, FLASH_MS};
+use awareness_system::{Noises, COMBAT_NOISE};
+
+/// A natural 20 always hits, and rolls the damage dice twice.
+const CRITICAL_ROLL: i32 = 20;
+/// A natural 1 always misses, even against something caught off guard.
+const FUMBLE_ROLL: i32 = 1;
+
+// bare fists, for anything with neither a weapon nor claws
+const UNARMED: DiceType = DiceType { n_dice: 1, die_type: 3, bonus: 0 };
 
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = ( Entities<'a>,
                         WriteStorage<'a, WantsToMelee>,
                         ReadStorage<'a, Name>,
                         ReadStorage<'a, CombatStats>,
                         WriteStorage<'a, SufferDamage>,
                         WriteExpect<'a, GameLog>,
+                        ReadExpect<'a, Entity>,
+                        WriteExpect<'a, ParticleBuilder>,
+                        ReadStorage<'a, Position>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        ReadStorage<'a, MeleeWeapon>,
+                        ReadStorage<'a, NaturalAttack>,
+                        ReadStorage<'a, Equipped>,
+                        ReadStorage<'a, Attributes>,
+                        WriteStorage<'a, Skills>,
+                        WriteExpect<'a, Noises>,
+                        ReadStorage<'a, Unaware>,
                       );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (entities, mut wants_melee, names, combat_stats, mut inflict_damage, mut log) = data;
+        let (
+            entities,
+            mut wants_melee,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            mut log,
+            player_entity,
+            mut particles,
+            positions,
+            mut rng,
+            weapons,
+            natural_attacks,
+            equipped,
+            attributes,
+            mut skills,
+            mut noises,
+            unaware,
+        ) = data;
 
-        for (_entity, wants_melee, name, stats) in (&entities, &wants_melee, &names, &combat_stats).join() {
+        for (entity, wants_melee, name, stats) in (&entities, &wants_melee, &names, &combat_stats).join() {
             if stats.current_hp > 0 {
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
                 if target_stats.current_hp > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    // a wielded weapon beats whatever nature provided
+                    let wielded = (&equipped, &weapons)
+                        .join()
+                        .find(|(equipped, _)| equipped.owner == entity)
+                        .map(|(_, weapon)| (weapon.damage, weapon.hit_bonus));
+                    let natural = natural_attacks
+                        .get(entity)
+                        .map(|attack| (attack.damage, attack.hit_bonus));
+                    let (damage_dice, hit_bonus) = wielded.or(natural).unwrap_or((UNARMED, 0));
+
+                    let attacker = attributes.get(entity);
+                    let to_hit = attacker.map_or(0, |a| a.to_hit());
+                    let damage_bonus = attacker.map_or(0, |a| a.damage_bonus());
+                    let armour_class = target_stats.armour_class(attributes.get(wants_melee.target));
+
+                    let skill = skills.get(entity).map_or(0, |s| s.level(Skill::Melee));
+
+                    let natural_roll = rng.roll_dice(1, 20);
+                    let attack_roll = natural_roll + to_hit + hit_bonus + skill;
+
+                    // the player can catch anything that hasn't noticed them
+                    let surprised = entity == *player_entity && unaware.get(wants_melee.target).is_some();
 
-                    if damage == 0 {
-                        log.entries.push(format!("{} is unable to hurt {}", &name.name, &target_name.name));
+                    if let Some(pos) = positions.get(wants_melee.target) {
+                        noises.make(entity, Point::new(pos.x, pos.y), COMBAT_NOISE);
+                    }
+
+                    let hit = if natural_roll == FUMBLE_ROLL {
+                        log.entry()
+                            .text(format!("{} ", &name.name))
+                            .coloured("fumbles", RGB::named(rltk::MAGENTA))
+                            .text(format!(" an attack on {}!", &target_name.name))
+                            .log();
+                        None
+                    } else if natural_roll == CRITICAL_ROLL {
+                        let damage = doubled_damage(&mut rng, damage_dice, damage_bonus);
+                        log.entry()
+                            .text(format!("{} lands a ", &name.name))
+                            .coloured("critical hit", RGB::named(rltk::YELLOW))
+                            .text(format!(" on {}, for ", &target_name.name))
+                            .coloured(format!("{} hp", damage), RGB::named(rltk::RED))
+                            .text("!")
+                            .log();
+                        Some((damage, RGB::named(rltk::YELLOW)))
+                    } else if surprised {
+                        let damage = doubled_damage(&mut rng, damage_dice, damage_bonus);
+                        log.entry()
+                            .text(format!("{} catches {} ", &name.name, &target_name.name))
+                            .coloured("off guard", RGB::named(rltk::CYAN))
+                            .text(", for ")
+                            .coloured(format!("{} hp", damage), RGB::named(rltk::RED))
+                            .text("!")
+                            .log();
+                        Some((damage, RGB::named(rltk::CYAN)))
+                    } else if attack_roll >= armour_class {
+                        let damage = i32::max(1, rng.roll(damage_dice) + damage_bonus);
+                        log.entry()
+                            .text(format!("{} hits {}, for ", &name.name, &target_name.name))
+                            .coloured(format!("{} hp", damage), RGB::named(rltk::RED))
+                            .text(".")
+                            .log();
+                        Some((damage, RGB::named(rltk::ORANGE)))
                     } else {
-                        log.entries.push(format!("{} hits {}, for {} hp.", &name.name, &target_name.name, damage));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        log.entry()
+                            .coloured(format!("{} misses {}.", &name.name, &target_name.name), RGB::named(rltk::GREY))
+                            .log();
+                        None
+                    };
+
+                    if let Some((damage, colour)) = hit {
+                        if let Some(pos) = positions.get(wants_melee.target) {
+                            particles.request(pos.x, pos.y, colour, RGB::named(rltk::BLACK), rltk::to_cp437('‼'), FLASH_MS);
+                        }
+                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage, entity == *player_entity);
                     }
+
+                    Skills::practise_for(&mut skills, &mut log, entity, entity == *player_entity, Skill::Melee);
                 }
             }
         }
 
         wants_melee.clear();
     }
-}
\ No newline at end of file
+}
+
+// critical hits and surprise attacks both roll the damage dice twice
+fn doubled_damage(rng: &mut RandomNumberGenerator, dice: DiceType, bonus: i32) -> i32 {
+    i32::max(1, rng.roll(dice) + rng.roll_dice(dice.n_dice, dice.die_type) + bonus)
+}
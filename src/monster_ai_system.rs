@@ -1,5 +1,10 @@
 use super::*;
-use rltk::Point;
+use factions::{FactionTable, Reaction};
+use pathing_system::PathingMaps;
+use rltk::{BaseMap, Point, RandomNumberGenerator};
+
+// how far out of its way a wandering monster will go for something to pick up
+const ITEM_INTEREST_RANGE: f32 = 12.0;
 
 pub struct MonsterAI {}
 
@@ -7,39 +12,163 @@ impl<'a> System<'a> for MonsterAI {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         WriteExpect<'a, Map>,
-        ReadExpect<'a, Point>,
-        ReadExpect<'a, Entity>,
         Entities<'a>,
         WriteStorage<'a, ViewShed>,
         ReadStorage<'a, Monster>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
-        ReadExpect<'a, RunState>,
+        ReadStorage<'a, MyTurn>,
         WriteStorage<'a, Confusion>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, Wandering>,
+        WriteStorage<'a, Hunting>,
+        WriteStorage<'a, Fleeing>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Faction>,
+        ReadExpect<'a, FactionTable>,
+        WriteExpect<'a, PathingMaps>,
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, Entity>,
+        ReadStorage<'a, Asleep>,
+        ReadStorage<'a, Unaware>,
+        ReadStorage<'a, UsesItems>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             mut map,
-            player_pos,
-            player_entity,
             entities,
             mut viewshed,
             monsters,
             mut position,
             mut wants_to_melee,
-            runstate,
+            turns,
             mut confused,
+            combat_stats,
+            mut wandering,
+            mut hunting,
+            mut fleeing,
+            mut rng,
+            factions,
+            faction_table,
+            mut pathing,
+            player_pos,
+            player_entity,
+            asleep,
+            unaware,
+            uses_items,
         ) = data;
 
-        if *runstate != RunState::MonsterTurn {
-            return;
+        // pick a state for every monster before anyone acts
+        for (entity, viewshed, _monster, _turn, stats, faction, pos) in (
+            &entities,
+            &viewshed,
+            &monsters,
+            &turns,
+            &combat_stats,
+            &factions,
+            &position,
+        )
+            .join()
+        {
+            if asleep.get(entity).is_some() {
+                continue;
+            }
+            // the player can't be chased by anything that hasn't noticed them
+            let is_unaware = unaware.get(entity).is_some();
+
+            let my_pos = Point::new(pos.x, pos.y);
+            let mut nearest_prey: Option<(Entity, Point, f32)> = None;
+            let mut hostiles: Vec<Point> = Vec::new();
+            let mut scary: Vec<Point> = Vec::new();
+
+            for tile in viewshed.visible_tiles.iter() {
+                for other in map.get_tile_content(tile.x, tile.y).iter() {
+                    if *other == entity || (is_unaware && *other == *player_entity) {
+                        continue;
+                    }
+                    if let Some(their_faction) = factions.get(*other) {
+                        match faction_table.reaction(&faction.name, &their_faction.name) {
+                            Reaction::Ignore => {}
+                            Reaction::Flee => scary.push(*tile),
+                            Reaction::Attack => {
+                                hostiles.push(*tile);
+                                let distance =
+                                    rltk::DistanceAlg::Pythagoras.distance2d(my_pos, *tile);
+                                let is_nearer = match nearest_prey {
+                                    None => true,
+                                    Some((_, _, best)) => distance < best,
+                                };
+                                if is_nearer {
+                                    nearest_prey = Some((*other, *tile, distance));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            let is_fleeing = fleeing.get(entity).is_some();
+            let badly_hurt = if is_fleeing {
+                stats.current_hp * 2 < stats.max_hp
+            } else {
+                stats.current_hp * 4 < stats.max_hp
+            };
+
+            if badly_hurt || !scary.is_empty() {
+                let mut threats = scary;
+                if badly_hurt {
+                    threats.append(&mut hostiles);
+                }
+
+                wandering.remove(entity);
+                hunting.remove(entity);
+                if let Some(flee) = fleeing.get_mut(entity) {
+                    // keep running from where the threats were last seen
+                    if !threats.is_empty() {
+                        flee.from = threats;
+                    }
+                } else {
+                    fleeing
+                        .insert(entity, Fleeing { from: threats })
+                        .expect("unable to insert flee state");
+                }
+            } else if let Some((target, target_pos, _)) = nearest_prey {
+                fleeing.remove(entity);
+                wandering.remove(entity);
+                hunting
+                    .insert(
+                        entity,
+                        Hunting {
+                            target,
+                            last_known_position: target_pos,
+                        },
+                    )
+                    .expect("unable to insert hunt state");
+            } else {
+                // hunters keep searching the last known position until they reach it
+                let still_hunting = match hunting.get(entity) {
+                    Some(hunt) => entities.is_alive(hunt.target),
+                    None => false,
+                };
+                if !still_hunting {
+                    fleeing.remove(entity);
+                    hunting.remove(entity);
+                    wandering
+                        .insert(entity, Wandering {})
+                        .expect("unable to insert wander state");
+                }
+            }
         }
 
-        for (entity, mut viewshed, _monster, mut pos) in
-            (&entities, &mut viewshed, &monsters, &mut position).join()
+        let mut lost_the_trail: Vec<Entity> = Vec::new();
+
+        for (entity, viewshed, _monster, _turn, pos) in
+            (&entities, &mut viewshed, &monsters, &turns, &mut position).join()
         {
-            let mut can_act = true;
+            if asleep.get(entity).is_some() {
+                continue;
+            }
 
             let is_confused = confused.get_mut(entity);
             if let Some(i_am_confused) = is_confused {
@@ -47,38 +176,121 @@ impl<'a> System<'a> for MonsterAI {
                 if i_am_confused.turns < 1 {
                     confused.remove(entity);
                 }
-                can_act = false;
+                continue;
             }
 
-            if can_act {
-                let distance =
-                    rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-                if distance < 1.5 {
+            let my_pos = Point::new(pos.x, pos.y);
+            let my_idx = map.xy_idx(pos.x, pos.y);
+
+            if let Some(flee) = fleeing.get(entity) {
+                let exit = if flee.from.iter().all(|threat| *threat == *player_pos) {
+                    pathing.away_from_player.step_down(&map, my_idx)
+                } else {
+                    let threats: Vec<usize> =
+                        flee.from.iter().map(|p| map.xy_idx(p.x, p.y)).collect();
+                    pathing.away_from(&map, &threats).step_down(&map, my_idx)
+                };
+
+                match exit {
+                    Some(exit) => {
+                        move_monster(&mut map, pos, viewshed, exit);
+                    }
+                    None => {
+                        // cornered, so fight back
+                        let cornered_by = flee.from.iter().find(|threat| {
+                            rltk::DistanceAlg::Pythagoras.distance2d(my_pos, **threat) < 1.5
+                        });
+                        if let Some(threat) = cornered_by {
+                            let victim = map
+                                .get_tile_content(threat.x, threat.y)
+                                .iter()
+                                .find(|e| combat_stats.get(**e).is_some());
+                            if let Some(victim) = victim {
+                                wants_to_melee
+                                    .insert(entity, WantsToMelee { target: *victim })
+                                    .expect("unable to insert an attack");
+                            }
+                        }
+                    }
+                }
+            } else if let Some(hunt) = hunting.get(entity) {
+                let target_pos = hunt.last_known_position;
+                // something investigating a noise doesn't know what it'll find
+                let can_see_target = viewshed.visible_tiles.contains(&target_pos)
+                    && !(unaware.get(entity).is_some() && hunt.target == *player_entity);
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(my_pos, target_pos);
+                if can_see_target && distance < 1.5 {
                     wants_to_melee
                         .insert(
                             entity,
                             WantsToMelee {
-                                target: *player_entity,
+                                target: hunt.target,
                             },
                         )
                         .expect("unable to insert an attack");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y),
-                        map.xy_idx(player_pos.x, player_pos.y),
-                        &mut *map,
-                    );
-
-                    if path.success && path.steps.len() > 1 {
-                        let (width, _) = map.get_dimensions();
-                        map.set_tile_as_unblocked(pos.x, pos.y);
-                        pos.x = path.steps[1] as i32 % width;
-                        pos.y = path.steps[1] as i32 / width;
-                        map.set_tile_as_blocked(pos.x, pos.y);
-                        viewshed.dirty = true;
+                } else if can_see_target && hunt.target == *player_entity {
+                    // everyone chasing the player shares one map
+                    if let Some(exit) = pathing.towards_player.step_down(&map, my_idx) {
+                        move_monster(&mut map, pos, viewshed, exit);
+                    }
+                } else if my_pos == target_pos {
+                    lost_the_trail.push(entity);
+                } else {
+                    // the target itself may be blocking the tile we want to reach
+                    let target_blocked = map.tile_is_blocked(target_pos.x, target_pos.y);
+                    map.set_tile_as_unblocked(target_pos.x, target_pos.y);
+                    let path =
+                        rltk::a_star_search(my_idx, map.xy_idx(target_pos.x, target_pos.y), &*map);
+                    if target_blocked {
+                        map.set_tile_as_blocked(target_pos.x, target_pos.y);
+                    }
+
+                    let (width, _) = map.get_dimensions();
+                    let next_step_free = path.success
+                        && path.steps.len() > 1
+                        && !map.tile_is_blocked(path.steps[1] as i32 % width, path.steps[1] as i32 / width);
+                    if next_step_free {
+                        move_monster(&mut map, pos, viewshed, path.steps[1]);
+                    } else {
+                        lost_the_trail.push(entity);
+                    }
+                }
+            } else if wandering.get(entity).is_some() {
+                // anything that uses items heads for the nearest one lying
+                // around, and MonsterItemAI picks it up once it gets there
+                let towards_item = if uses_items.contains(entity)
+                    && pathing.towards_items.distance(my_idx) <= ITEM_INTEREST_RANGE
+                {
+                    pathing.towards_items.step_down(&map, my_idx)
+                } else {
+                    None
+                };
+
+                if let Some(exit) = towards_item {
+                    move_monster(&mut map, pos, viewshed, exit);
+                } else if rng.roll_dice(1, 2) == 1 {
+                    let exits = map.get_available_exits(my_idx);
+                    if let Some(exit) = rng.random_slice_entry(&exits) {
+                        move_monster(&mut map, pos, viewshed, exit.0);
                     }
                 }
             }
         }
+
+        for entity in lost_the_trail.iter() {
+            hunting.remove(*entity);
+            wandering
+                .insert(*entity, Wandering {})
+                .expect("unable to insert wander state");
+        }
     }
 }
+
+fn move_monster(map: &mut Map, pos: &mut Position, viewshed: &mut ViewShed, idx: usize) {
+    let (width, _) = map.get_dimensions();
+    map.set_tile_as_unblocked(pos.x, pos.y);
+    pos.x = idx as i32 % width;
+    pos.y = idx as i32 / width;
+    map.set_tile_as_blocked(pos.x, pos.y);
+    viewshed.dirty = true;
+}
@@ -0,0 +1,166 @@
+use super::*;
+use rltk::Point;
+
+/// Lets monsters that know how to use items do so. Anything that uses an item
+/// spends its turn on it, so it is taken away before `MonsterAI` sees it.
+pub struct MonsterItemAI {}
+
+impl<'a> System<'a> for MonsterItemAI {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        ReadStorage<'a, ViewShed>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Hunting>,
+        ReadStorage<'a, Fleeing>,
+        ReadStorage<'a, UsesItems>,
+        ReadStorage<'a, ThrowsItems>,
+        ReadStorage<'a, Item>,
+        ReadStorage<'a, InBackPack>,
+        ReadStorage<'a, ProvidesHealing>,
+        ReadStorage<'a, Ranged>,
+        ReadStorage<'a, InflictsDamage>,
+        ReadStorage<'a, Confusion>,
+        ReadStorage<'a, AreaOfEffect>,
+        WriteStorage<'a, WantsToUseItem>,
+        WriteStorage<'a, WantsToPickupItem>,
+        ReadStorage<'a, Asleep>,
+        ReadStorage<'a, Unaware>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            entities,
+            viewsheds,
+            monsters,
+            positions,
+            mut turns,
+            combat_stats,
+            hunting,
+            fleeing,
+            uses_items,
+            throws_items,
+            items,
+            backpack,
+            healing,
+            ranged,
+            inflicts_damage,
+            confusion,
+            aoe,
+            mut wants_use,
+            mut wants_pickup,
+            asleep,
+            unaware,
+        ) = data;
+
+        let mut busy: Vec<Entity> = Vec::new();
+
+        for (entity, viewshed, _monster, _turn, stats, pos, _uses) in (
+            &entities,
+            &viewsheds,
+            &monsters,
+            &turns,
+            &combat_stats,
+            &positions,
+            &uses_items,
+        )
+            .join()
+        {
+            // too dazed, or too deeply asleep, to work anything
+            if confusion.get(entity).is_some() || asleep.get(entity).is_some() {
+                continue;
+            }
+
+            let my_pos = Point::new(pos.x, pos.y);
+            let carried: Vec<Entity> = (&entities, &backpack)
+                .join()
+                .filter(|(_, pack)| pack.owner == entity)
+                .map(|(item, _)| item)
+                .collect();
+
+            // patch up before it's too late
+            if stats.current_hp * 2 < stats.max_hp {
+                if let Some(potion) = carried.iter().find(|item| healing.get(**item).is_some()) {
+                    wants_use
+                        .insert(
+                            entity,
+                            WantsToUseItem {
+                                item: *potion,
+                                target: None,
+                            },
+                        )
+                        .expect("unable to insert item use");
+                    busy.push(entity);
+                    continue;
+                }
+            }
+
+            // nothing throws at a noise it hasn't seen the source of
+            if throws_items.get(entity).is_some() && unaware.get(entity).is_none() {
+                if let Some(hunt) = hunting.get(entity) {
+                    let target = hunt.last_known_position;
+                    let distance = rltk::DistanceAlg::Pythagoras.distance2d(my_pos, target);
+                    let throwable = carried.iter().find(|item| {
+                        let in_range = match ranged.get(**item) {
+                            Some(ranged) => distance <= ranged.range as f32,
+                            None => false,
+                        };
+                        let does_harm = inflicts_damage.get(**item).is_some()
+                            || confusion.get(**item).is_some();
+                        // nobody throws a fireball at their own feet
+                        let too_close = match aoe.get(**item) {
+                            Some(aoe) => distance <= aoe.radius as f32,
+                            None => false,
+                        };
+                        in_range && does_harm && !too_close
+                    });
+
+                    if let Some(throwable) = throwable {
+                        if viewshed.visible_tiles.contains(&target) {
+                            wants_use
+                                .insert(
+                                    entity,
+                                    WantsToUseItem {
+                                        item: *throwable,
+                                        target: Some(target),
+                                    },
+                                )
+                                .expect("unable to insert item use");
+                            busy.push(entity);
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            // anything lying around is worth taking, given a quiet moment
+            if hunting.get(entity).is_none() && fleeing.get(entity).is_none() {
+                let loot = map
+                    .get_tile_content(pos.x, pos.y)
+                    .iter()
+                    .find(|e| items.get(**e).is_some() && positions.get(**e).is_some());
+                if let Some(loot) = loot {
+                    wants_pickup
+                        .insert(
+                            entity,
+                            WantsToPickupItem {
+                                collected_by: entity,
+                                item: *loot,
+                            },
+                        )
+                        .expect("unable to insert pickup");
+                    busy.push(entity);
+                }
+            }
+        }
+
+        for entity in busy.iter() {
+            turns.remove(*entity);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use super::*;
+use rltk::{Rltk, RGB};
+
+/// How long a flash of particles stays on screen.
+pub const FLASH_MS: f32 = 200.0;
+
+struct ParticleRequest {
+    x: i32,
+    y: i32,
+    foreground: RGB,
+    background: RGB,
+    glyph: u8,
+    lifetime_ms: f32,
+}
+
+/// Particles asked for during a turn, created together once every system
+/// that might want one has run.
+#[derive(Default)]
+pub struct ParticleBuilder {
+    requests: Vec<ParticleRequest>,
+}
+
+impl ParticleBuilder {
+    pub fn request(&mut self, x: i32, y: i32, foreground: RGB, background: RGB, glyph: u8, lifetime_ms: f32) {
+        self.requests.push(ParticleRequest {
+            x,
+            y,
+            foreground,
+            background,
+            glyph,
+            lifetime_ms,
+        });
+    }
+}
+
+pub struct ParticleSpawnSystem {}
+
+impl<'a> System<'a> for ParticleSpawnSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        WriteStorage<'a, Position>,
+                        WriteStorage<'a, Renderable>,
+                        WriteStorage<'a, ParticleLifetime>,
+                        WriteExpect<'a, ParticleBuilder>,
+                      );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut positions, mut renderables, mut particles, mut builder) = data;
+
+        for request in builder.requests.drain(..) {
+            let particle = entities.create();
+            positions.insert(particle, Position { x: request.x, y: request.y }).expect("Unable to insert position");
+            renderables.insert(particle, Renderable {
+                glyph: request.glyph,
+                foreground: request.foreground,
+                background: request.background,
+                // drawn over everything else
+                render_order: -1,
+            }).expect("Unable to insert renderable");
+            particles.insert(particle, ParticleLifetime { lifetime_ms: request.lifetime_ms }).expect("Unable to insert lifetime");
+        }
+    }
+}
+
+/// Ages every particle by the time the last frame took, and removes the ones
+/// that have had their time.
+pub fn cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
+    let mut dead: Vec<Entity> = Vec::new();
+    {
+        let mut particles = ecs.write_storage::<ParticleLifetime>();
+        let entities = ecs.entities();
+        for (entity, particle) in (&entities, &mut particles).join() {
+            particle.lifetime_ms -= ctx.frame_time_ms;
+            if particle.lifetime_ms < 0.0 {
+                dead.push(entity);
+            }
+        }
+    }
+
+    for particle in dead.iter() {
+        ecs.delete_entity(*particle).expect("Unable to delete particle");
+    }
+}
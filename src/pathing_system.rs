@@ -0,0 +1,72 @@
+use specs::prelude::*;
+use super::*;
+use distance_map::DistanceMap;
+use std::collections::HashMap;
+
+/// Distance maps shared by every creature, rebuilt once per turn.
+pub struct PathingMaps {
+    pub towards_player: DistanceMap,
+    pub away_from_player: DistanceMap,
+    pub towards_items: DistanceMap,
+    // maps away from anything other than just the player, built the first
+    // time something flees from them in a turn
+    away_from_threats: HashMap<Vec<usize>, DistanceMap>,
+}
+
+impl PathingMaps {
+    /// Maps with no goals at all, until the first turn fills them in.
+    pub fn new(map: &Map) -> PathingMaps {
+        let nowhere = DistanceMap::new(map, &[]);
+        PathingMaps {
+            towards_player: nowhere.clone(),
+            away_from_player: nowhere.clone(),
+            towards_items: nowhere,
+            away_from_threats: HashMap::new(),
+        }
+    }
+
+    /// A map leading away from every one of `threats`, shared by everything
+    /// fleeing the same threats until the next turn.
+    pub fn away_from(&mut self, map: &Map, threats: &[usize]) -> &DistanceMap {
+        let mut key = threats.to_vec();
+        key.sort_unstable();
+        key.dedup();
+        self.away_from_threats
+            .entry(key)
+            .or_insert_with_key(|threats| DistanceMap::walls_only(map, threats).flee(map))
+    }
+}
+
+pub struct PathingSystem {}
+
+impl<'a> System<'a> for PathingSystem {
+    type SystemData = ( ReadExpect<'a, Map>,
+                        ReadExpect<'a, Point>,
+                        ReadExpect<'a, RunState>,
+                        WriteExpect<'a, PathingMaps>,
+                        ReadStorage<'a, Item>,
+                        ReadStorage<'a, Position>,
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, runstate, mut pathing, items, positions) = data;
+
+        // the player only moves on their own turn
+        if *runstate != RunState::PlayerTurn && *runstate != RunState::PreRun {
+            return;
+        }
+
+        pathing.away_from_threats.clear();
+
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        pathing.towards_player = DistanceMap::walls_only(&map, &[player_idx]);
+        pathing.away_from_player = pathing.towards_player.flee(&map);
+
+        // only items lying on the floor; anything in a backpack has no position
+        let item_tiles: Vec<usize> = (&items, &positions)
+            .join()
+            .map(|(_, pos)| map.xy_idx(pos.x, pos.y))
+            .collect();
+        pathing.towards_items = DistanceMap::walls_only(&map, &item_tiles);
+    }
+}
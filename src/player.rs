@@ -1,14 +1,45 @@
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::{BaseMap, Point, RandomNumberGenerator, Rltk, RGB};
 use specs::prelude::*;
 use specs_derive::Component;
+use serde::{Serialize, Deserialize};
 use std::cmp::{max, min};
+use std::collections::HashSet;
 
 use super::*;
+use awareness_system::{walking_noise, Noises};
+use distance_map::DistanceMap;
+use factions::{FactionTable, Reaction};
+use keymap::{Action, Context, Keymap};
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
-fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+// a rest that goes on this long isn't going to get anywhere
+const MAX_REST_TURNS: i32 = 500;
+
+/// Something the player keeps doing, a turn at a time, until it is finished
+/// or something interrupts it.
+#[derive(PartialEq, Copy, Clone)]
+enum Activity {
+    Resting { turns: i32 },
+    Exploring,
+    Travelling { destination: Point },
+}
+
+/// What the player is busy with between key presses.
+#[derive(Default)]
+pub struct PlayerActivity {
+    activity: Option<Activity>,
+    last_hp: i32,
+    // items already seen, so that only new ones interrupt exploring
+    noticed: HashSet<Entity>,
+    // hostiles in view last turn; only newcomers are a reason to stop
+    hostiles: HashSet<Entity>,
+}
+
+fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
+    let (delta_x, delta_y) = stumble(ecs, delta_x, delta_y);
+
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<ViewShed>();
@@ -16,8 +47,12 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let map = ecs.fetch::<Map>();
     let combat_stats = ecs.read_storage::<CombatStats>();
 
+    let vendors = ecs.read_storage::<Vendor>();
+
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let skills = ecs.read_storage::<Skills>();
+    let mut noises = ecs.write_resource::<Noises>();
 
     for (entity, _player, pos, viewshed) in (&entities, &mut players, &mut positions, &mut viewsheds).join() {
         let (width, height) = map.get_dimensions();
@@ -25,67 +60,122 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
         let y = min(height - 1, max(0, pos.y + delta_y));
 
         for potential_target in map.get_tile_content(x, y).iter() {
+            if vendors.get(*potential_target).is_some() {
+                return RunState::Trade { vendor: *potential_target, selling: false, selection: 0 };
+            }
+
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee.insert(entity, WantsToMelee{ target: *potential_target }).expect("Add target failed");
-                return;
+                return RunState::PlayerTurn;
             }
         }
 
-        if !map.tile_is_blocked(x, y) {
+        let destination = map.xy_idx(x, y);
+        let can_move = map
+            .get_available_exits(map.xy_idx(pos.x, pos.y))
+            .iter()
+            .any(|exit| exit.0 == destination);
+
+        if can_move {
             pos.x = x;
             pos.y = y;
             player_pos.x = x;
             player_pos.y = y;
 
             viewshed.dirty = true;
+
+            let stealth = skills.get(entity).map_or(0, |s| s.level(Skill::Stealth));
+            noises.make(entity, Point::new(x, y), walking_noise(stealth));
         }
     }
+
+    RunState::PlayerTurn
+}
+
+// half the time, a confused player goes somewhere other than where they meant to
+fn stumble(ecs: &mut World, delta_x: i32, delta_y: i32) -> (i32, i32) {
+    let player_entity = *ecs.fetch::<Entity>();
+    if !ecs.read_storage::<Confusion>().contains(player_entity) {
+        return (delta_x, delta_y);
+    }
+
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    if rng.roll_dice(1, 2) == 1 {
+        return (delta_x, delta_y);
+    }
+    let directions = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+    let direction = directions[rng.range(0, directions.len() as i32) as usize];
+    ecs.write_resource::<GameLog>().entry()
+        .text("You ")
+        .coloured("stumble", RGB::named(rltk::MAGENTA))
+        .text(" about in confusion.")
+        .log();
+    direction
 }
 
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    match ctx.key {
+    if gs.ecs.fetch::<PlayerActivity>().activity.is_some() {
+        // any key or click stops whatever the player is busy with
+        if ctx.key.is_some() || ctx.left_click {
+            gs.ecs.fetch_mut::<PlayerActivity>().activity = None;
+            return RunState::AwaitingInput;
+        }
+        return continue_activity(&mut gs.ecs);
+    }
+
+    if ctx.left_click {
+        let mouse_pos = ctx.mouse_pos();
+        let clicked = Camera::new(&gs.ecs).screen_to_world(Point::new(mouse_pos.0, mouse_pos.1));
+        if let Some(destination) = clicked {
+            return travel_to(&mut gs.ecs, destination);
+        }
+    }
+
+    let action = gs.ecs.fetch::<Keymap>().action(ctx.key, Context::Game);
+    match action {
         None => return RunState::AwaitingInput,
-        Some(key) => match key {
-            VirtualKeyCode::Left |
-            VirtualKeyCode::Numpad4 |
-            VirtualKeyCode::H => try_move_player(-1, 0, &mut gs.ecs),
+        Some(action) => {
+            if let Some((delta_x, delta_y)) = action.direction() {
+                return try_move_player(delta_x, delta_y, &mut gs.ecs);
+            }
+
+            match action {
+                Action::PickUp => get_item(&mut gs.ecs),
 
-            VirtualKeyCode::Right |
-            VirtualKeyCode::Numpad6 |
-            VirtualKeyCode::L => try_move_player(1, 0, &mut gs.ecs),
+                Action::Inventory => return RunState::ShowInventory { selection: 0 },
 
-            VirtualKeyCode::Up |
-            VirtualKeyCode::Numpad8 |
-            VirtualKeyCode::K => try_move_player(0, -1, &mut gs.ecs),
+                Action::Drop => return RunState::ShowDropItem { selection: 0 },
 
-            VirtualKeyCode::Down |
-            VirtualKeyCode::Numpad2 |
-            VirtualKeyCode::J => try_move_player(0, 1, &mut gs.ecs),
+                Action::ShowLog => return RunState::ShowLog { offset: 0 },
 
-            // diagonals
-            VirtualKeyCode::Numpad9 |
-            VirtualKeyCode::Y => try_move_player(1, -1, &mut gs.ecs),
+                Action::CharacterSheet => return RunState::CharacterSheet,
 
-            VirtualKeyCode::Numpad7 |
-            VirtualKeyCode::U => try_move_player(-1, -1, &mut gs.ecs),
+                Action::LookAround => {
+                    return RunState::LookAt {
+                        cursor: *gs.ecs.fetch::<Point>(),
+                    }
+                }
 
-            VirtualKeyCode::Numpad3 |
-            VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
+                Action::Wait => return RunState::PlayerTurn,
 
-            VirtualKeyCode::Numpad1 |
-            VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
+                Action::Rest => return start_activity(&mut gs.ecs, Activity::Resting { turns: 0 }),
 
-            VirtualKeyCode::G => get_item(&mut gs.ecs),
+                Action::Explore => return start_activity(&mut gs.ecs, Activity::Exploring),
 
-            VirtualKeyCode::I => return RunState::ShowInventory,
+                Action::Travel => {
+                    return RunState::ChooseDestination {
+                        cursor: *gs.ecs.fetch::<Point>(),
+                    }
+                }
 
-            VirtualKeyCode::D => return RunState::ShowDropItem,
+                Action::ShowBindings => return RunState::ShowBindings,
 
-            VirtualKeyCode::Escape => return RunState::SaveGame,
+                Action::SaveAndQuit => return RunState::SaveGame,
 
-            _ => return RunState::AwaitingInput,
-        },
+                _ => return RunState::AwaitingInput,
+            }
+        }
     }
 
     RunState::PlayerTurn
@@ -107,10 +197,244 @@ fn get_item(ecs: &mut World) {
     }
 
     match target_item {
-        None => gamelog.entries.push("There is nothing here to pick up.".to_string()),
+        None => gamelog.log("There is nothing here to pick up."),
         Some(item) => {
             let mut pickup = ecs.write_storage::<WantsToPickupItem>();
             pickup.insert(*player_entity, WantsToPickupItem{ collected_by: *player_entity, item }).expect("Unable to insert want to pickup");
         }
     }
 }
+
+fn start_activity(ecs: &mut World, activity: Activity) -> RunState {
+    let (hp, max_hp) = player_hp(ecs);
+    if matches!(activity, Activity::Resting { .. }) && hp >= max_hp {
+        ecs.fetch_mut::<GameLog>().log("You're already at full health.");
+        return RunState::AwaitingInput;
+    }
+    let hostiles: HashSet<Entity> = visible_hostiles(ecs).into_iter().collect();
+    if !hostiles.is_empty() && !matches!(activity, Activity::Travelling { .. }) {
+        ecs.fetch_mut::<GameLog>().log("Not with enemies in sight!");
+        return RunState::AwaitingInput;
+    }
+
+    let noticed = visible_items(ecs).into_iter().collect();
+    {
+        let mut busy = ecs.fetch_mut::<PlayerActivity>();
+        busy.activity = Some(activity);
+        busy.last_hp = hp;
+        busy.noticed = noticed;
+        busy.hostiles = hostiles;
+    }
+
+    continue_activity(ecs)
+}
+
+/// Sets the player walking to a tile they know about, a step per turn.
+pub fn travel_to(ecs: &mut World, destination: Point) -> RunState {
+    let known_floor = {
+        let map = ecs.fetch::<Map>();
+        map.tile_is_revealed(destination.x, destination.y)
+            && map.get_tile(destination.x, destination.y) == TileType::Floor
+    };
+    if !known_floor {
+        ecs.fetch_mut::<GameLog>().log("You don't know the way there.");
+        return RunState::AwaitingInput;
+    }
+    if destination == *ecs.fetch::<Point>() {
+        return RunState::AwaitingInput;
+    }
+
+    start_activity(ecs, Activity::Travelling { destination })
+}
+
+/// Takes the next turn of whatever the player is busy with, or stops if
+/// there is a reason to.
+fn continue_activity(ecs: &mut World) -> RunState {
+    let activity = match ecs.fetch::<PlayerActivity>().activity {
+        Some(activity) => activity,
+        None => return RunState::AwaitingInput,
+    };
+    let (hp, max_hp) = player_hp(ecs);
+
+    if hp < ecs.fetch::<PlayerActivity>().last_hp {
+        return stop_activity(ecs, "You're hurt!");
+    }
+    ecs.fetch_mut::<PlayerActivity>().last_hp = hp;
+
+    let hostiles = visible_hostiles(ecs);
+    let newcomer = {
+        let busy = ecs.fetch::<PlayerActivity>();
+        hostiles.iter().find(|hostile| !busy.hostiles.contains(hostile)).copied()
+    };
+    ecs.fetch_mut::<PlayerActivity>().hostiles = hostiles.into_iter().collect();
+    if let Some(monster) = newcomer {
+        let (name, colour) = {
+            let names = ecs.read_storage::<Name>();
+            let renderables = ecs.read_storage::<Renderable>();
+            let name = names.get(monster).map_or(String::new(), |n| n.name.clone());
+            let colour = renderables.get(monster).map_or(RGB::named(rltk::WHITE), |r| r.foreground);
+            (name, colour)
+        };
+        ecs.fetch_mut::<PlayerActivity>().activity = None;
+        ecs.fetch_mut::<GameLog>()
+            .entry()
+            .text("The ")
+            .coloured(name, colour)
+            .text(" comes into view.")
+            .log();
+        return RunState::AwaitingInput;
+    }
+
+    match activity {
+        Activity::Resting { turns } => {
+            if hp >= max_hp {
+                return stop_activity(ecs, "You feel rested.");
+            }
+            if turns >= MAX_REST_TURNS {
+                return stop_activity(ecs, "You can't rest any longer.");
+            }
+            ecs.fetch_mut::<PlayerActivity>().activity = Some(Activity::Resting { turns: turns + 1 });
+            RunState::PlayerTurn
+        }
+        Activity::Exploring => {
+            let new_items: Vec<Entity> = {
+                let busy = ecs.fetch::<PlayerActivity>();
+                visible_items(ecs).into_iter().filter(|item| !busy.noticed.contains(item)).collect()
+            };
+            if let Some(item) = new_items.first() {
+                let (name, colour) = {
+                    let names = ecs.read_storage::<Name>();
+                    let renderables = ecs.read_storage::<Renderable>();
+                    let identification = ecs.fetch::<Identification>();
+                    let name = names.get(*item).map_or(String::new(), |n| identification.name_of(&n.name));
+                    let colour = renderables.get(*item).map_or(RGB::named(rltk::WHITE), |r| r.foreground);
+                    (name, colour)
+                };
+                {
+                    let mut busy = ecs.fetch_mut::<PlayerActivity>();
+                    busy.activity = None;
+                    busy.noticed.extend(new_items.iter());
+                }
+                ecs.fetch_mut::<GameLog>()
+                    .entry()
+                    .text("You spot the ")
+                    .coloured(name, colour)
+                    .text(".")
+                    .log();
+                return RunState::AwaitingInput;
+            }
+
+            match next_step_to_explore(ecs) {
+                Some((delta_x, delta_y)) => try_move_player(delta_x, delta_y, ecs),
+                None => stop_activity(ecs, "There's nowhere left to explore."),
+            }
+        }
+        Activity::Travelling { destination } => {
+            if destination == *ecs.fetch::<Point>() {
+                ecs.fetch_mut::<PlayerActivity>().activity = None;
+                return RunState::AwaitingInput;
+            }
+
+            match next_step_towards(ecs, destination) {
+                Some((delta_x, delta_y)) => try_move_player(delta_x, delta_y, ecs),
+                None => stop_activity(ecs, "Something is in the way."),
+            }
+        }
+    }
+}
+
+fn stop_activity(ecs: &mut World, reason: &str) -> RunState {
+    ecs.fetch_mut::<PlayerActivity>().activity = None;
+    ecs.fetch_mut::<GameLog>().log(reason);
+    RunState::AwaitingInput
+}
+
+/// Which way to step to get closer to the edge of what the player has seen,
+/// if any of it can be reached.
+fn next_step_to_explore(ecs: &World) -> Option<(i32, i32)> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let (width, height) = map.get_dimensions();
+
+    // known floor next to something unknown; the player can't go by what
+    // they haven't seen. The outer edge of the map is never seen, so it
+    // doesn't count.
+    let mut frontier: Vec<usize> = Vec::new();
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            if !map.tile_is_revealed(x, y) || map.get_tile(x, y) != TileType::Floor {
+                continue;
+            }
+            let borders_unknown = (y - 1..=y + 1)
+                .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                .any(|(nx, ny)| {
+                    nx > 0 && nx < width - 1 && ny > 0 && ny < height - 1 && !map.tile_is_revealed(nx, ny)
+                });
+            if borders_unknown {
+                frontier.push(map.xy_idx(x, y));
+            }
+        }
+    }
+
+    let distance_map = DistanceMap::new(&map, &frontier);
+    let step = distance_map.step_down(&map, map.xy_idx(player_pos.x, player_pos.y))?;
+    Some((step as i32 % width - player_pos.x, step as i32 / width - player_pos.y))
+}
+
+/// Which way to step along the shortest path to the destination. The path
+/// is found afresh every step, so it works around anything that moves into
+/// the way.
+fn next_step_towards(ecs: &World, destination: Point) -> Option<(i32, i32)> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let (width, _) = map.get_dimensions();
+
+    let path = rltk::a_star_search(
+        map.xy_idx(player_pos.x, player_pos.y),
+        map.xy_idx(destination.x, destination.y),
+        &*map,
+    );
+    if !path.success || path.steps.len() < 2 {
+        return None;
+    }
+
+    let step = path.steps[1] as i32;
+    Some((step % width - player_pos.x, step / width - player_pos.y))
+}
+
+fn player_hp(ecs: &World) -> (i32, i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    combat_stats
+        .get(*player_entity)
+        .map_or((0, 0), |stats| (stats.current_hp, stats.max_hp))
+}
+
+/// Everything in view that would attack the player.
+fn visible_hostiles(ecs: &World) -> Vec<Entity> {
+    let map = ecs.fetch::<Map>();
+    let faction_table = ecs.fetch::<FactionTable>();
+    let entities = ecs.entities();
+    let factions = ecs.read_storage::<Faction>();
+    let positions = ecs.read_storage::<Position>();
+
+    (&entities, &factions, &positions)
+        .join()
+        .filter(|(_, faction, _)| faction_table.reaction(&faction.name, factions::PLAYER) == Reaction::Attack)
+        .filter(|(_, _, pos)| map.tile_is_visible(pos.x, pos.y))
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
+
+fn visible_items(ecs: &World) -> Vec<Entity> {
+    let map = ecs.fetch::<Map>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+
+    (&entities, &items, &positions)
+        .join()
+        .filter(|(_, _, pos)| map.tile_is_visible(pos.x, pos.y))
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
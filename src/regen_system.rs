@@ -0,0 +1,111 @@
+use specs::prelude::*;
+use rltk::RGB;
+use super::*;
+use factions::{FactionTable, Reaction};
+
+/// How many turns the player takes to heal a point at level 1. Every level
+/// gained knocks a turn off, down to `FASTEST_REGEN_INTERVAL`.
+const BASE_REGEN_INTERVAL: i32 = 12;
+const FASTEST_REGEN_INTERVAL: i32 = 3;
+
+/// Heals the player, and anything else that regenerates, as turns go by.
+/// Only runs once per player turn, however many turns the monsters get.
+pub struct RegenSystem {}
+
+impl<'a> System<'a> for RegenSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, Entity>,
+                        ReadExpect<'a, RunState>,
+                        ReadExpect<'a, RunStats>,
+                        ReadExpect<'a, FactionTable>,
+                        ReadExpect<'a, Map>,
+                        WriteExpect<'a, GameLog>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, Faction>,
+                        ReadStorage<'a, Experience>,
+                        ReadStorage<'a, Regenerates>,
+                        WriteStorage<'a, Invigorated>,
+                        WriteStorage<'a, CombatStats>,
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            runstate,
+            run_stats,
+            faction_table,
+            map,
+            mut gamelog,
+            positions,
+            factions,
+            experience,
+            regenerates,
+            mut invigorated,
+            mut combat_stats,
+        ) = data;
+
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+        let turn = run_stats.turns;
+
+        // nobody gets their breath back with something hostile watching, but
+        // anything lurking in the dark goes unnoticed
+        let in_danger = (&factions, &positions).join().any(|(faction, pos)| {
+            faction_table.reaction(&faction.name, factions::PLAYER) == Reaction::Attack
+                && map.tile_is_visible(pos.x, pos.y)
+        });
+        if !in_danger {
+            let level = experience.get(*player_entity).map_or(1, |exp| exp.level);
+            let interval = i32::max(FASTEST_REGEN_INTERVAL, BASE_REGEN_INTERVAL + 1 - level);
+            let boosted = invigorated.get(*player_entity).is_some();
+            if let Some(stats) = combat_stats.get_mut(*player_entity) {
+                if turn % regen_interval(interval, boosted) == 0 {
+                    regenerate(stats);
+                }
+            }
+        }
+
+        for (entity, regen, stats) in (&entities, &regenerates, &mut combat_stats).join() {
+            let boosted = invigorated.get(entity).is_some();
+            if turn % regen_interval(regen.interval, boosted) == 0 {
+                regenerate(stats);
+            }
+        }
+
+        let mut worn_off: Vec<Entity> = Vec::new();
+        for (entity, status) in (&entities, &mut invigorated).join() {
+            status.turns -= 1;
+            if status.turns < 1 {
+                worn_off.push(entity);
+            }
+        }
+        for entity in worn_off.iter() {
+            invigorated.remove(*entity);
+            if *entity == *player_entity {
+                gamelog.entry()
+                    .text("You no longer feel ")
+                    .coloured("invigorated", RGB::named(rltk::GREEN))
+                    .text(".")
+                    .log();
+            }
+        }
+    }
+}
+
+// being invigorated heals twice as fast
+fn regen_interval(interval: i32, boosted: bool) -> i32 {
+    if boosted {
+        i32::max(1, interval / 2)
+    } else {
+        i32::max(1, interval)
+    }
+}
+
+fn regenerate(stats: &mut CombatStats) {
+    if stats.current_hp > 0 {
+        stats.current_hp = i32::min(stats.max_hp, stats.current_hp + 1);
+    }
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RunStats {
+    pub turns: i32,
+    pub kills: i32,
+}
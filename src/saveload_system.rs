@@ -1,5 +1,136 @@
 use super::*;
+use specs::error::NoError;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+};
+use std::fs;
+use std::fs::File;
+use std::path::Path;
 
-pub fn save_game(world: &mut World) {
-    
-}
\ No newline at end of file
+const SAVE_PATH: &str = "./savegame.json";
+
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )
+        .unwrap();
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<NoError, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &mut $data.0,
+            &mut $data.1,
+            &mut $data.2,
+            &mut $de,
+        )
+        .unwrap();
+        )*
+    };
+}
+
+pub fn save_game(ecs: &mut World) {
+    let map = (*ecs.fetch::<Map>()).clone();
+    let log = (*ecs.fetch::<GameLog>()).clone();
+    let stats = (*ecs.fetch::<RunStats>()).clone();
+    let identification = (*ecs.fetch::<Identification>()).clone();
+    let helper = ecs
+        .create_entity()
+        .with(SerializationHelper {
+            map,
+            log,
+            stats,
+            identification,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    {
+        let data = (
+            ecs.entities(),
+            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+        );
+
+        let writer = File::create(SAVE_PATH).expect("Unable to create save file");
+        let mut serializer = serde_json::Serializer::new(writer);
+        serialize_individually!(
+            ecs, serializer, data, Position, Renderable, LeftMover, Player, ViewShed, Monster,
+            Wandering, Hunting, Fleeing, Initiative, MyTurn, Faction, Name, BlocksTile,
+            CombatStats, Experience, WantsToMelee, SufferDamage, Description, Item, ProvidesHealing, InBackPack,
+            WantsToPickupItem, WantsToUseItem, WantsToDropItem, Consumable, Value, Currency, Vendor, Identifies, UsesItems, ThrowsItems, LootTable, Ranged,
+            InflictsDamage, AreaOfEffect, Confusion, Invigorated, GrantsInvigoration, Regenerates, MeleeWeapon, NaturalAttack, Equipped, Attributes, Skills, TechItem, Asleep, Unaware, LightSource, RevealsMap, Detection, GrantsDetection, LeavesWreckage, SerializationHelper
+        );
+    }
+
+    ecs.delete_entity(helper).expect("Unable to delete save helper");
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVE_PATH).exists()
+}
+
+pub fn load_game(ecs: &mut World) {
+    {
+        let everything: Vec<Entity> = ecs.entities().join().collect();
+        for entity in everything.iter() {
+            ecs.delete_entity(*entity).expect("Unable to delete entity");
+        }
+    }
+
+    let data = fs::read_to_string(SAVE_PATH).expect("Unable to read save file");
+    let mut de = serde_json::Deserializer::from_str(&data);
+
+    {
+        let mut d = (
+            &mut ecs.entities(),
+            &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+        );
+        deserialize_individually!(
+            ecs, de, d, Position, Renderable, LeftMover, Player, ViewShed, Monster, Wandering,
+            Hunting, Fleeing, Initiative, MyTurn, Faction, Name, BlocksTile, CombatStats, Experience,
+            WantsToMelee, SufferDamage, Description, Item, ProvidesHealing, InBackPack, WantsToPickupItem,
+            WantsToUseItem, WantsToDropItem, Consumable, Value, Currency, Vendor, Identifies, UsesItems, ThrowsItems, LootTable, Ranged, InflictsDamage, AreaOfEffect,
+            Confusion, Invigorated, GrantsInvigoration, Regenerates, MeleeWeapon, NaturalAttack, Equipped, Attributes, Skills, TechItem, Asleep, Unaware, LightSource, RevealsMap, Detection, GrantsDetection, LeavesWreckage, SerializationHelper
+        );
+    }
+
+    let mut helper_entity: Option<Entity> = None;
+    {
+        let entities = ecs.entities();
+        let helpers = ecs.read_storage::<SerializationHelper>();
+        let players = ecs.read_storage::<Player>();
+        let positions = ecs.read_storage::<Position>();
+
+        for (entity, helper) in (&entities, &helpers).join() {
+            *ecs.write_resource::<Map>() = helper.map.clone();
+            *ecs.write_resource::<GameLog>() = helper.log.clone();
+            *ecs.write_resource::<RunStats>() = helper.stats.clone();
+            *ecs.write_resource::<Identification>() = helper.identification.clone();
+            helper_entity = Some(entity);
+        }
+
+        for (entity, _player, pos) in (&entities, &players, &positions).join() {
+            *ecs.write_resource::<Point>() = Point::new(pos.x, pos.y);
+            *ecs.write_resource::<Entity>() = entity;
+        }
+    }
+
+    ecs.delete_entity(helper_entity.expect("Save file has no helper"))
+        .expect("Unable to delete save helper");
+}
+
+pub fn delete_save() {
+    if does_save_exist() {
+        fs::remove_file(SAVE_PATH).expect("Unable to delete save file");
+    }
+}
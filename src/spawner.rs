@@ -1,12 +1,24 @@
 use super::*;
+use dice::dice;
+use initiative_system::{NORMAL_SPEED, TURN_COST};
 use rltk::{RandomNumberGenerator, RGB};
 use specs::saveload::MarkedBuilder;
 
 const MAX_MONSTERS_PER_ROOM: i32 = 4;
 const MAX_ITEMS_PER_ROOM: i32 = 2;
+const STARTING_CREDITS: i32 = 30;
+const TRADER_STOCK: i32 = 5;
+// a ship's crew flight suit turns the odd blow
+const PLAYER_DEFENSE: i32 = 2;
+// the lamp on the player's suit
+const TORCH_RANGE: i32 = 6;
 
+/// Creates the player with average attributes, ready for character
+/// creation to change them.
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
-    ecs.create_entity()
+    let attributes = Attributes::new(AVERAGE_ATTRIBUTE, AVERAGE_ATTRIBUTE, AVERAGE_ATTRIBUTE, AVERAGE_ATTRIBUTE);
+    let player = ecs
+        .create_entity()
         .with(Position {
             x: player_x,
             y: player_y,
@@ -20,15 +32,57 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         .with(Player {})
         .with(ViewShed {
             visible_tiles: Vec::new(),
-            range: 8,
+            range: attributes.view_range(),
             dirty: true,
         })
+        .with(LightSource {
+            colour: RGB::from_f32(1.0, 0.9, 0.6),
+            range: TORCH_RANGE,
+        })
         .with(Name {
             name: "Player".to_string(),
         })
-        .with(CombatStats::new(30, 2, 5))
+        .with(Faction {
+            name: factions::PLAYER.to_string(),
+        })
+        .with(CombatStats::new(attributes.max_hp(), PLAYER_DEFENSE))
+        .with(attributes)
+        .with(Skills::default())
+        .with(Experience { xp: 0, level: 1 })
+        .with(Initiative {
+            speed: NORMAL_SPEED,
+            energy: 0,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build()
+        .build();
+
+    let money = credits(ecs, player_x, player_y, STARTING_CREDITS);
+    put_in_backpack(ecs, money, player);
+
+    let knife = combat_knife(ecs, player_x, player_y);
+    put_in_backpack(ecs, knife, player);
+    ecs.write_storage::<Equipped>()
+        .insert(knife, Equipped { owner: player })
+        .expect("Unable to equip knife");
+
+    player
+}
+
+/// Brings everything worked out from someone's attributes up to date, healing
+/// them fully. Only meant for before the game starts.
+pub fn apply_attributes(ecs: &mut World, entity: Entity) {
+    let attributes = match ecs.read_storage::<Attributes>().get(entity) {
+        Some(attributes) => attributes.clone(),
+        None => return,
+    };
+    if let Some(stats) = ecs.write_storage::<CombatStats>().get_mut(entity) {
+        stats.max_hp = attributes.max_hp();
+        stats.current_hp = stats.max_hp;
+    }
+    if let Some(viewshed) = ecs.write_storage::<ViewShed>().get_mut(entity) {
+        viewshed.range = attributes.view_range();
+        viewshed.dirty = true;
+    }
 }
 
 pub fn spawn_room(ecs: &mut World, map_width: i32, room: &Rect) {
@@ -71,13 +125,13 @@ pub fn spawn_room(ecs: &mut World, map_width: i32, room: &Rect) {
     for idx in monster_spawn_points.iter() {
         let x = *idx % map_width;
         let y = *idx / map_width;
-        random_monster(ecs, x as i32, y as i32);
+        random_monster(ecs, x, y);
     }
 
     for idx in item_spawn_points.iter() {
         let x = *idx % map_width;
         let y = *idx / map_width;
-        random_item(ecs, x as i32, y as i32);
+        random_item(ecs, x, y);
     }
 }
 
@@ -85,22 +139,211 @@ fn random_monster(ecs: &mut World, x: i32, y: i32) {
     let roll: i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 2);
+        roll = rng.roll_dice(1, 5);
     }
     match roll {
-        1 => orc(ecs, x, y),
-        _ => goblin(ecs, x, y),
+        1 | 2 => orc(ecs, x, y),
+        3 | 4 => goblin(ecs, x, y),
+        _ => drone(ecs, x, y),
     }
 }
 
 fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
+    let orc = monster(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('o'),
+        "Orc",
+        "A hulking raider in scavenged armour, always spoiling for a fight.",
+        factions::ORCS,
+        Attributes::new(14, 10, 8, 7),
+        1,
+        NaturalAttack {
+            name: "cleaver".to_string(),
+            damage: dice("1d6"),
+            hit_bonus: 0,
+        },
+        NORMAL_SPEED,
+    );
+    ecs.write_storage::<UsesItems>()
+        .insert(orc, UsesItems {})
+        .expect("Unable to insert item use");
+    // orc flesh knits back together almost as fast as it's cut
+    ecs.write_storage::<Regenerates>()
+        .insert(orc, Regenerates { interval: 4 })
+        .expect("Unable to insert regeneration");
+    sometimes_asleep(ecs, orc);
+    ecs.write_storage::<LootTable>()
+        .insert(
+            orc,
+            LootTable {
+                drops: vec![
+                    ("Health Potion".to_string(), 2),
+                    ("Scroll of Magic Missle".to_string(), 1),
+                    ("Credits".to_string(), 3),
+                ],
+                nothing: 5,
+            },
+        )
+        .expect("Unable to insert loot table");
 }
+
 fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
+    let goblin = monster(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('g'),
+        "Goblin",
+        "A wiry scavenger who picks over wrecks and anyone it finds in them.",
+        factions::GOBLINS,
+        Attributes::new(10, 14, 7, 10),
+        1,
+        NaturalAttack {
+            name: "claws".to_string(),
+            damage: dice("1d4+1"),
+            hit_bonus: 0,
+        },
+        NORMAL_SPEED,
+    );
+    ecs.write_storage::<UsesItems>()
+        .insert(goblin, UsesItems {})
+        .expect("Unable to insert item use");
+    ecs.write_storage::<ThrowsItems>()
+        .insert(goblin, ThrowsItems {})
+        .expect("Unable to insert item use");
+    sometimes_asleep(ecs, goblin);
+    ecs.write_storage::<LootTable>()
+        .insert(
+            goblin,
+            LootTable {
+                drops: vec![
+                    ("Scroll of Fireball".to_string(), 1),
+                    ("Scroll of Confusion".to_string(), 1),
+                    ("Scroll of Identify".to_string(), 1),
+                    ("Credits".to_string(), 3),
+                ],
+                nothing: 6,
+            },
+        )
+        .expect("Unable to insert loot table");
+
+    // some come with something already in their pockets
+    let roll = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 3);
+    if roll == 1 {
+        let item = random_item(ecs, x, y);
+        put_in_backpack(ecs, item, goblin);
+    }
+}
+
+// drones never sleep, but anything living might be caught napping
+fn sometimes_asleep(ecs: &mut World, entity: Entity) {
+    let roll = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 3);
+    if roll == 1 {
+        ecs.write_storage::<Asleep>()
+            .insert(entity, Asleep {})
+            .expect("Unable to insert sleep");
+    }
+}
+
+fn drone(ecs: &mut World, x: i32, y: i32) {
+    // flimsy, but acts twice for every player turn
+    let drone = monster(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('d'),
+        "Drone",
+        "A fast, fragile security drone that still thinks this ship is its to guard.",
+        factions::MACHINES,
+        Attributes::new(8, 16, 3, 12),
+        0,
+        NaturalAttack {
+            name: "stun prod".to_string(),
+            damage: dice("1d4"),
+            hit_bonus: 1,
+        },
+        NORMAL_SPEED * 2,
+    );
+    // its sensor eye glows, which gives it away in the dark
+    ecs.write_storage::<LightSource>()
+        .insert(drone, LightSource { colour: RGB::from_f32(0.3, 0.8, 1.0), range: 2 })
+        .expect("Unable to insert light");
+    ecs.write_storage::<LeavesWreckage>()
+        .insert(drone, LeavesWreckage { item: "Drone Parts".to_string() })
+        .expect("Unable to insert wreckage");
 }
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S) {
+/// Puts lamps on the walls of some rooms. On a ship that has lost its power,
+/// only the odd emergency lamp still works.
+pub fn wall_lamps(ecs: &mut World, map: &Map) {
+    let (chance, name, description, colour, range) = if map.is_powered() {
+        (2, "Wall Lamp", "A caged lamp, bolted to the wall.", RGB::from_f32(1.0, 1.0, 0.8), 6)
+    } else {
+        (4, "Emergency Lamp", "A red lamp, still running off its own battery.", RGB::from_f32(0.9, 0.1, 0.1), 4)
+    };
+
+    for i in 0..map.get_room_count() {
+        let room = map.get_room(i);
+        let spot = {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            if rng.roll_dice(1, chance) != 1 {
+                continue;
+            }
+            // somewhere along the top wall that a corridor hasn't cut through
+            let spots: Vec<i32> = (room.x1 + 1..=room.x2)
+                .filter(|x| map.get_tile(*x, room.y1) == TileType::Wall)
+                .collect();
+            rng.random_slice_entry(&spots).copied()
+        };
+        if let Some(x) = spot {
+            wall_lamp(ecs, x, room.y1, name, description, colour, range);
+        }
+    }
+}
+
+fn wall_lamp(ecs: &mut World, x: i32, y: i32, name: &str, description: &str, colour: RGB, range: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('☼'),
+            foreground: colour,
+            background: RGB::named(rltk::BLACK),
+            render_order: 3,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Description {
+            text: description.to_string(),
+        })
+        .with(LightSource { colour, range })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn monster<S: ToString>(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    glyph: u8,
+    name: S,
+    description: S,
+    faction: S,
+    attributes: Attributes,
+    defense: i32,
+    attack: NaturalAttack,
+    speed: i32,
+) -> Entity {
+    // stagger starting energy so monsters don't all move in lockstep
+    let energy: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        energy = rng.range(0, TURN_COST);
+    }
+
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -111,107 +354,416 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S) {
         })
         .with(ViewShed {
             visible_tiles: Vec::new(),
-            range: 8,
+            range: attributes.view_range(),
             dirty: true,
         })
         .with(Monster {})
+        .with(Wandering {})
+        .with(Unaware {})
+        .with(Faction {
+            name: faction.to_string(),
+        })
         .with(Name {
             name: name.to_string(),
         })
+        .with(Description {
+            text: description.to_string(),
+        })
         .with(BlocksTile {})
-        .with(CombatStats::new(16, 1, 4))
+        .with(CombatStats::new(attributes.max_hp(), defense))
+        .with(attributes)
+        .with(attack)
+        .with(Initiative { speed, energy })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn random_item(ecs: &mut World, x: i32, y: i32) {
+fn random_item(ecs: &mut World, x: i32, y: i32) -> Entity {
     let roll: i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 4);
+        roll = rng.roll_dice(1, 9);
     }
     match roll {
         1 => health_potion(ecs, x, y),
         2 => fireball_scroll(ecs, x, y),
         3 => confusion_scroll(ecs, x, y),
+        4 => identify_scroll(ecs, x, y),
+        5 => stim_pack(ecs, x, y),
+        6 => vibro_blade(ecs, x, y),
+        7 => ship_schematic(ecs, x, y),
+        8 => motion_tracker(ecs, x, y),
         _ => magic_missile_scroll(ecs, x, y),
-    };
+    }
+}
+
+/// Spawns an item by its real name, for anything that refers to items by
+/// name, such as loot tables.
+pub fn named_item(ecs: &mut World, name: &str, x: i32, y: i32) -> Option<Entity> {
+    match name {
+        "Health Potion" => Some(health_potion(ecs, x, y)),
+        "Stim Pack" => Some(stim_pack(ecs, x, y)),
+        "Ship Schematic" => Some(ship_schematic(ecs, x, y)),
+        "Motion Tracker" => Some(motion_tracker(ecs, x, y)),
+        "Combat Knife" => Some(combat_knife(ecs, x, y)),
+        "Vibro-Blade" => Some(vibro_blade(ecs, x, y)),
+        "Scroll of Magic Missle" => Some(magic_missile_scroll(ecs, x, y)),
+        "Scroll of Fireball" => Some(fireball_scroll(ecs, x, y)),
+        "Scroll of Confusion" => Some(confusion_scroll(ecs, x, y)),
+        "Scroll of Identify" => Some(identify_scroll(ecs, x, y)),
+        "Drone Parts" => Some(drone_parts(ecs, x, y)),
+        "Credits" => {
+            let amount = ecs.write_resource::<RandomNumberGenerator>().roll_dice(2, 6);
+            Some(credits(ecs, x, y, amount))
+        }
+        _ => None,
+    }
+}
+
+/// Moves an item off the floor and into someone's backpack.
+pub fn put_in_backpack(ecs: &mut World, item: Entity, owner: Entity) {
+    ecs.write_storage::<Position>().remove(item);
+    ecs.write_storage::<InBackPack>()
+        .insert(item, InBackPack { owner })
+        .expect("Unable to insert backpack entry");
+}
+
+/// A peaceful merchant who buys and sells from a stock of random items.
+pub fn trader(ecs: &mut World, x: i32, y: i32) {
+    let trader = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('T'),
+            foreground: RGB::named(rltk::GOLD),
+            background: RGB::named(rltk::BLACK),
+            render_order: 1,
+        })
+        .with(Name {
+            name: "Trader".to_string(),
+        })
+        .with(Description {
+            text: "A travelling merchant who will buy or sell almost anything.".to_string(),
+        })
+        .with(Vendor {})
+        .with(BlocksTile {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    for _i in 0..TRADER_STOCK {
+        let item = random_item(ecs, x, y);
+        put_in_backpack(ecs, item, trader);
+    }
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
+pub fn credits(ecs: &mut World, x: i32, y: i32, amount: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('$'),
+            foreground: RGB::named(rltk::GOLD),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Credits".to_string(),
+        })
+        .with(Description {
+            text: "Universal credit chips, good anywhere there's someone to take them.".to_string(),
+        })
+        .with(Item {})
+        .with(Currency { amount })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Leaves the corpse of a dead monster where it fell.
+pub fn corpse(ecs: &mut World, monster_name: &str, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('%'),
+            foreground: RGB::named(rltk::DARK_RED),
+            background: RGB::named(rltk::BLACK),
+            render_order: 3,
+        })
+        .with(Name {
+            name: format!("{} corpse", monster_name),
+        })
+        .with(Description {
+            text: format!("All that's left of an unlucky {}.", monster_name.to_lowercase()),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn health_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let colour = ecs.fetch::<Identification>().colour_of("Health Potion", RGB::named(rltk::MAGENTA));
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('i'),
-            foreground: RGB::named(rltk::MAGENTA),
+            foreground: colour,
             background: RGB::named(rltk::BLACK),
             render_order: 2,
         })
         .with(Name {
             name: "Health Potion".to_string(),
         })
+        .with(Description {
+            text: "A vial of liquid that knits wounds closed.".to_string(),
+        })
         .with(Item {})
+        .with(Value { price: 15 })
         .with(Consumable {})
         .with(ProvidesHealing { heal_amount: 8 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
+fn combat_knife(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph: rltk::to_cp437(')'),
+            glyph: rltk::to_cp437('/'),
+            foreground: RGB::named(rltk::LIGHT_GREY),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Combat Knife".to_string(),
+        })
+        .with(Description {
+            text: "Standard issue for ship's crew. Short, sharp and well balanced.".to_string(),
+        })
+        .with(Item {})
+        .with(Value { price: 10 })
+        .with(MeleeWeapon {
+            damage: dice("1d6"),
+            hit_bonus: 1,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn vibro_blade(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('/'),
             foreground: RGB::named(rltk::CYAN),
             background: RGB::named(rltk::BLACK),
             render_order: 2,
         })
+        .with(Name {
+            name: "Vibro-Blade".to_string(),
+        })
+        .with(Description {
+            text: "A heavy blade that hums as it shakes itself through armour.".to_string(),
+        })
+        .with(Item {})
+        .with(Value { price: 40 })
+        .with(MeleeWeapon {
+            damage: dice("1d8+1"),
+            hit_bonus: 0,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn stim_pack(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('+'),
+            foreground: RGB::named(rltk::GREEN),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Stim Pack".to_string(),
+        })
+        .with(Description {
+            text: "An auto-injector of military stimulants that speed the body's own healing.".to_string(),
+        })
+        .with(Item {})
+        .with(Value { price: 20 })
+        .with(Consumable {})
+        .with(GrantsInvigoration { turns: 50 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn ship_schematic(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('?'),
+            foreground: RGB::named(rltk::SKYBLUE),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Ship Schematic".to_string(),
+        })
+        .with(Description {
+            text: "A tablet holding the deck plans of this very ship.".to_string(),
+        })
+        .with(Item {})
+        .with(TechItem {})
+        .with(Value { price: 40 })
+        .with(Consumable {})
+        .with(RevealsMap {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn motion_tracker(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('•'),
+            foreground: RGB::named(rltk::MAGENTA),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Motion Tracker".to_string(),
+        })
+        .with(Description {
+            text: "A handheld scanner that picks up anything moving aboard, until its charge runs out.".to_string(),
+        })
+        .with(Item {})
+        .with(TechItem {})
+        .with(Value { price: 30 })
+        .with(Consumable {})
+        .with(GrantsDetection { turns: 10 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let colour = ecs.fetch::<Identification>().colour_of("Scroll of Magic Missle", RGB::named(rltk::CYAN));
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            foreground: colour,
+            background: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
         .with(Name {
             name: "Scroll of Magic Missle".to_string(),
         })
+        .with(Description {
+            text: "Reading it aloud hurls a bolt of force at a single target.".to_string(),
+        })
         .with(Item {})
+        .with(TechItem {})
+        .with(Value { price: 20 })
         .with(Ranged { range: 6 })
         .with(InflictsDamage { damage: 8 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
+fn fireball_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let colour = ecs.fetch::<Identification>().colour_of("Scroll of Fireball", RGB::named(rltk::ORANGE));
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437(')'),
-            foreground: RGB::named(rltk::ORANGE),
+            foreground: colour,
             background: RGB::named(rltk::BLACK),
             render_order: 2,
         })
         .with(Name {
             name: "Scroll of Fireball".to_string(),
         })
+        .with(Description {
+            text: "Reading it aloud sets off a ball of fire that engulfs everything near where it lands.".to_string(),
+        })
         .with(Item {})
+        .with(TechItem {})
+        .with(Value { price: 40 })
         .with(Ranged { range: 6 })
         .with(InflictsDamage { damage: 20 })
         .with(AreaOfEffect { radius: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
+fn confusion_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let colour = ecs.fetch::<Identification>().colour_of("Scroll of Confusion", RGB::named(rltk::PINK));
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437(')'),
-            foreground: RGB::named(rltk::PINK),
+            foreground: colour,
             background: RGB::named(rltk::BLACK),
             render_order: 2,
         })
         .with(Name {
             name: "Scroll of Confusion".to_string(),
         })
+        .with(Description {
+            text: "Reading it aloud leaves everything near where it lands staggering about in a daze.".to_string(),
+        })
         .with(Item {})
+        .with(TechItem {})
+        .with(Value { price: 30 })
         .with(Ranged { range: 6 })
         .with(Confusion { turns: 4 })
         .with(AreaOfEffect { radius: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+fn identify_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let colour = ecs.fetch::<Identification>().colour_of("Scroll of Identify", RGB::named(rltk::WHITE));
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            foreground: colour,
+            background: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Scroll of Identify".to_string(),
+        })
+        .with(Description {
+            text: "Reading it reveals what everything else in your backpack really is.".to_string(),
+        })
+        .with(Item {})
+        .with(TechItem {})
+        .with(Value { price: 25 })
+        .with(Consumable {})
+        .with(Identifies {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn drone_parts(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('%'),
+            foreground: RGB::named(rltk::GREY),
+            background: RGB::named(rltk::BLACK),
+            render_order: 3,
+        })
+        .with(Name {
+            name: "Drone Parts".to_string(),
+        })
+        .with(Description {
+            text: "Jagged scrap from a wrecked drone, heavy enough to hurt when thrown.".to_string(),
+        })
+        .with(Item {})
+        .with(Value { price: 5 })
+        .with(Consumable {})
+        .with(Ranged { range: 4 })
+        .with(InflictsDamage { damage: 4 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
@@ -0,0 +1,123 @@
+use super::*;
+use inventory_system::{has_room_for, stack_items};
+use rltk::RGB;
+
+/// What a vendor pays for something they would sell for `price`.
+pub fn sell_price(price: i32) -> i32 {
+    i32::max(1, price / 2)
+}
+
+/// The item holding all of someone's money, if they have any.
+pub fn purse(ecs: &World, owner: Entity) -> Option<Entity> {
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackPack>();
+    let currency = ecs.read_storage::<Currency>();
+
+    (&entities, &backpack, &currency)
+        .join()
+        .find(|(_, pack, _)| pack.owner == owner)
+        .map(|(item, _, _)| item)
+}
+
+pub fn credits(ecs: &World, owner: Entity) -> i32 {
+    match purse(ecs, owner) {
+        Some(purse) => ecs.read_storage::<Currency>().get(purse).map_or(0, |c| c.amount),
+        None => 0,
+    }
+}
+
+/// Moves a vendor's item into the player's backpack, if they can pay for it
+/// and have room to carry it.
+pub fn buy(ecs: &mut World, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let price = ecs.read_storage::<Value>().get(item).map_or(0, |v| v.price);
+    let (name, colour) = item_name(ecs, item);
+
+    if credits(ecs, player_entity) < price {
+        ecs.write_resource::<GameLog>().log("You can't afford that.");
+        return;
+    }
+    if !has_room(ecs, player_entity, item) {
+        ecs.write_resource::<GameLog>().log("Your backpack is full.");
+        return;
+    }
+
+    add_credits(ecs, player_entity, -price);
+    ecs.write_storage::<InBackPack>()
+        .insert(item, InBackPack { owner: player_entity })
+        .expect("Unable to insert backpack entry");
+
+    ecs.write_resource::<GameLog>()
+        .entry()
+        .text("You buy the ")
+        .coloured(name, colour)
+        .text(" for ")
+        .coloured(format!("{} credits", price), RGB::named(rltk::GOLD))
+        .text(".")
+        .log();
+}
+
+/// Hands one of the player's items to a vendor, who pays for it.
+pub fn sell(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let price = match ecs.read_storage::<Value>().get(item) {
+        Some(value) => sell_price(value.price),
+        None => return,
+    };
+    let (name, colour) = item_name(ecs, item);
+
+    ecs.write_storage::<InBackPack>()
+        .insert(item, InBackPack { owner: vendor })
+        .expect("Unable to insert backpack entry");
+    ecs.write_storage::<Equipped>().remove(item);
+    add_credits(ecs, player_entity, price);
+
+    ecs.write_resource::<GameLog>()
+        .entry()
+        .text("You sell the ")
+        .coloured(name, colour)
+        .text(" for ")
+        .coloured(format!("{} credits", price), RGB::named(rltk::GOLD))
+        .text(".")
+        .log();
+}
+
+fn add_credits(ecs: &mut World, owner: Entity, amount: i32) {
+    match purse(ecs, owner) {
+        Some(purse) => {
+            if let Some(currency) = ecs.write_storage::<Currency>().get_mut(purse) {
+                currency.amount += amount;
+            }
+        }
+        None => {
+            let money = spawner::credits(ecs, 0, 0, amount);
+            spawner::put_in_backpack(ecs, money, owner);
+        }
+    }
+}
+
+fn has_room(ecs: &World, owner: Entity, item: Entity) -> bool {
+    let entities = ecs.entities();
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackPack>();
+    let consumables = ecs.read_storage::<Consumable>();
+
+    let stacks = stack_items(
+        (&entities, &backpack, &names)
+            .join()
+            .filter(|(_, pack, _)| pack.owner == owner)
+            .map(|(item, _, name)| (item, name.name.as_str(), consumables.contains(item))),
+    );
+    let name = names.get(item).map_or("", |n| n.name.as_str());
+    has_room_for(&stacks, name, consumables.contains(item))
+}
+
+fn item_name(ecs: &World, item: Entity) -> (String, RGB) {
+    let names = ecs.read_storage::<Name>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let identification = ecs.fetch::<Identification>();
+
+    let name = names.get(item).map_or(String::new(), |n| identification.name_of(&n.name));
+    let colour = renderables.get(item).map_or(RGB::named(rltk::WHITE), |r| r.foreground);
+    (name, colour)
+}
@@ -26,8 +26,11 @@ impl<'a> System<'a> for VisibilitySystem {
             if let Some(_p) = p {
                 map.clear_visible_tiles();
                 for vis in viewshed.visible_tiles.iter() {
-                    map.reveal_tile(vis.x, vis.y);
-                    map.mark_tile_as_visible(vis.x, vis.y);
+                    // nothing can be made out in the dark, not even the walls
+                    if map.tile_is_lit(vis.x, vis.y) {
+                        map.reveal_tile(vis.x, vis.y);
+                        map.mark_tile_as_visible(vis.x, vis.y);
+                    }
                 }
             }
         }